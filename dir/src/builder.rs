@@ -0,0 +1,91 @@
+use crate::{NodeId, Tree};
+
+/// Fluent builder for constructing trees in nested form.
+///
+/// ```
+/// use dir::TreeBuilder;
+///
+/// let mut a = None;
+/// let (tree, root) = TreeBuilder::new("root")
+///     .child("a", |b| b.leaf("a1").leaf("a2"))
+///     .capture(&mut a)
+///     .leaf("b")
+///     .build();
+/// assert_eq!(tree.parent(a.unwrap()), Some(root));
+/// ```
+#[derive(Debug)]
+pub struct TreeBuilder<T> {
+    tree: Tree<T>,
+    root: NodeId,
+    cursor: NodeId,
+    last: NodeId,
+}
+
+impl<T> TreeBuilder<T> {
+    /// Start a tree with the given root data.
+    pub fn new(root: T) -> Self {
+        let mut tree = Tree::new();
+        let root = tree.set_root(root);
+        Self { tree, root, cursor: root, last: root }
+    }
+
+    /// Add a childless node under the current node.
+    pub fn leaf(mut self, data: T) -> Self {
+        self.last = self.tree.add_child(self.cursor, data);
+        self
+    }
+
+    /// Add a node under the current node and populate it with `f`.
+    pub fn child<F>(mut self, data: T, f: F) -> Self
+    where
+        F: FnOnce(Self) -> Self,
+    {
+        let parent = self.cursor;
+        let id = self.tree.add_child(parent, data);
+        self.cursor = id;
+        self.last = id;
+        let mut built = f(self);
+        built.cursor = parent;
+        built.last = id;
+        built
+    }
+
+    /// Record the id of the node most recently added at this level.
+    pub fn capture(self, out: &mut Option<NodeId>) -> Self {
+        *out = Some(self.last);
+        self
+    }
+
+    /// Finish building, returning the tree and its root id.
+    pub fn build(self) -> (Tree<T>, NodeId) {
+        (self.tree, self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_matches_imperative_tree() {
+        let mut expected = Tree::new();
+        let root = expected.set_root("root");
+        let a = expected.add_child(root, "a");
+        expected.add_child(a, "a1");
+        let a2 = expected.add_child(a, "a2");
+        let b = expected.add_child(root, "b");
+        expected.add_child(b, "b1");
+
+        let (mut got_a, mut got_a2) = (None, None);
+        let (tree, got_root) = TreeBuilder::new("root")
+            .child("a", |n| n.leaf("a1").leaf("a2").capture(&mut got_a2))
+            .capture(&mut got_a)
+            .child("b", |n| n.leaf("b1"))
+            .build();
+
+        assert_eq!(tree, expected);
+        assert_eq!(got_root, root);
+        assert_eq!(got_a, Some(a));
+        assert_eq!(got_a2, Some(a2));
+    }
+}
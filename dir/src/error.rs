@@ -0,0 +1,57 @@
+use std::fmt;
+use crate::NodeId;
+
+/// Errors returned by fallible [`Tree`](crate::Tree) operations.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TreeError {
+    /// The operation needs a root but the tree is empty.
+    NoRoot,
+    /// The node needs a grandparent but is the root or a child of the root.
+    NoGrandparent(NodeId),
+    /// Two nodes were expected to share a parent but do not.
+    NotSibling { node: NodeId, sibling: NodeId },
+    /// The operation needs two disjoint subtrees but `ancestor` contains `descendant`.
+    Nested { ancestor: NodeId, descendant: NodeId },
+    /// A new child order for the node is not a permutation of its children.
+    NotPermutation(NodeId),
+    /// `child` was expected to be a child of `parent` but is not.
+    NotChild { parent: NodeId, child: NodeId },
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::NoRoot => f.write_str("tree has no root"),
+            TreeError::NoGrandparent(id) => write!(f, "node {} has no grandparent", id.0),
+            TreeError::NotSibling { node, sibling } => {
+                write!(f, "node {} is not a sibling of node {}", sibling.0, node.0)
+            }
+            TreeError::Nested { ancestor, descendant } => {
+                write!(f, "node {} is inside the subtree of node {}", descendant.0, ancestor.0)
+            }
+            TreeError::NotPermutation(id) => write!(f, "order is not a permutation of the children of node {}", id.0),
+            TreeError::NotChild { parent, child } => write!(f, "node {} is not a child of node {}", child.0, parent.0),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tree;
+
+    fn relabel(t: &mut Tree<&'static str>) -> Result<(), Box<dyn std::error::Error>> {
+        t.set_root_data("new")?;
+        Ok(())
+    }
+
+    #[test]
+    fn propagates_into_boxed_error() {
+        let err = relabel(&mut Tree::new()).unwrap_err();
+        assert_eq!(err.to_string(), "tree has no root");
+        assert_eq!(err.downcast_ref::<TreeError>(), Some(&TreeError::NoRoot));
+    }
+}
@@ -0,0 +1,77 @@
+//! Minimal glob matching for `/`-separated paths.
+//!
+//! Supported syntax: `?` matches one character other than `/`, `*` matches
+//! any run of characters other than `/`, and `**` matches anything,
+//! including `/`. A `**/` prefix may also match nothing, so `**/*.rs`
+//! matches `main.rs` as well as `src/main.rs`.
+
+/// Whether `text` matches the glob `pattern` in full.
+///
+/// Results are memoised per position in `pattern` and `text`, so runs of
+/// wildcards take polynomial rather than exponential time.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let mut memo = vec![None; (p.len() + 1) * (t.len() + 1)];
+    Matcher { p: &p, t: &t, memo: &mut memo }.matches(0, 0)
+}
+
+struct Matcher<'a> {
+    p: &'a [char],
+    t: &'a [char],
+    /// Outcome of `matches(pi, ti)`, at `pi * (t.len() + 1) + ti`.
+    memo: &'a mut [Option<bool>],
+}
+
+impl Matcher<'_> {
+    /// Whether `p[pi..]` matches `t[ti..]`.
+    fn matches(&mut self, pi: usize, ti: usize) -> bool {
+        let key = pi * (self.t.len() + 1) + ti;
+        if let Some(done) = self.memo[key] {
+            return done;
+        }
+        let (p, t) = (&self.p[pi..], &self.t[ti..]);
+        let result = match p {
+            [] => t.is_empty(),
+            ['*', '*', '/', ..] => {
+                self.matches(pi + 3, ti) || (0..t.len()).any(|i| t[i] == '/' && self.matches(pi + 3, ti + i + 1))
+            }
+            ['*', '*', ..] => (0..=t.len()).any(|i| self.matches(pi + 2, ti + i)),
+            ['*', ..] => (0..=t.len())
+                .take_while(|&i| i == 0 || t[i - 1] != '/')
+                .any(|i| self.matches(pi + 1, ti + i)),
+            ['?', ..] => matches!(t.first(), Some(&c) if c != '/') && self.matches(pi + 1, ti + 1),
+            [c, ..] => t.first() == Some(c) && self.matches(pi + 1, ti + 1),
+        };
+        self.memo[key] = Some(result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("src/*.rs", "src/main.rs"));
+        assert!(glob_match("**/*.rs", "main.rs"));
+        assert!(glob_match("**/*.rs", "a/b/c.rs"));
+        assert!(glob_match("a/**", "a/b/c"));
+        assert!(glob_match("?.txt", "a.txt"));
+        assert!(!glob_match("?.txt", "ab.txt"));
+        assert!(!glob_match("?", "/"));
+    }
+
+    #[test]
+    fn many_stars_stay_fast() {
+        let name = "a".repeat(60);
+        let start = std::time::Instant::now();
+        assert!(!glob_match("*a*a*a*a*a*a*a*b", &name));
+        assert!(!glob_match("**a**a**a**a**a**a**a**b", &name));
+        assert!(glob_match("*a*a*a*a*a*a*a*", &name));
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
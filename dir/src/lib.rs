@@ -1,3 +1,8 @@
+mod builder;
+pub mod error;
+pub mod glob;
 mod tree;
 
-pub use tree::{Tree, NodeId};
+pub use builder::TreeBuilder;
+pub use error::TreeError;
+pub use tree::{Tree, NodeId, DepthStats, Connectors, NestedNode, DfsIter, WalkAction};
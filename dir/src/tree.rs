@@ -1,4 +1,12 @@
+use std::collections::hash_map::{Entry, HashMap};
+use std::cmp::Ordering;
 use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::FusedIterator;
+use std::rc::Rc;
+use crate::error::TreeError;
+use crate::glob::glob_match;
 
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
@@ -6,10 +14,80 @@ use serde::{Serialize, Deserialize};
 /// Stable handle to a node inside the tree arena.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct NodeId(pub usize);
 
+/// Aggregate depth figures for a tree, as returned by [`Tree::depth_stats`].
+///
+/// Depths are counted from the root at 0. All fields are zero for an empty tree.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DepthStats {
+    /// Depth of the deepest node.
+    pub max: usize,
+    /// Depth of the shallowest leaf.
+    pub min_leaf: usize,
+    /// Sum of the depths of all nodes.
+    pub sum: usize,
+    /// Number of nodes.
+    pub count: usize,
+    /// Mean node depth.
+    pub mean: f64,
+}
+
+/// An owned, recursive copy of a tree, as returned by [`Tree::to_nested`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NestedNode<U> {
+    pub value: U,
+    pub children: Vec<NestedNode<U>>,
+}
+
+/// What [`Tree::visit_mut`] should do after visiting a node.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WalkAction {
+    /// Go on to the node's children.
+    Continue,
+    /// Remove the node's children and carry on with the rest of the tree.
+    SkipChildren,
+    /// End the walk.
+    Stop,
+}
+
+/// Glyphs used to draw the branches of a formatted tree.
+///
+/// Each piece should have the same display width so that columns line up.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Connectors<'a> {
+    /// Before a child that has later siblings, e.g. `├── `.
+    pub branch: &'a str,
+    /// Before the last child of a node, e.g. `└── `.
+    pub last: &'a str,
+    /// Indent under a child that has later siblings, e.g. `│   `.
+    pub vertical: &'a str,
+    /// Indent under the last child of a node.
+    pub space: &'a str,
+}
+
+impl Connectors<'static> {
+    /// Box-drawing connectors, as used by `tree`.
+    pub fn unicode() -> Self {
+        Self { branch: "├── ", last: "└── ", vertical: "│   ", space: "    " }
+    }
+
+    /// Plain ASCII connectors for terminals without box-drawing glyphs.
+    pub fn ascii() -> Self {
+        Self { branch: "|-- ", last: "`-- ", vertical: "|   ", space: "    " }
+    }
+}
+
+impl Default for Connectors<'static> {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
 /// Internal node representation.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct Node<T> {
     data: T,
@@ -18,19 +96,104 @@ struct Node<T> {
 }
 
 /// A safe, generic rooted tree.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tree<T> {
     nodes: Vec<Option<Node<T>>>,
     root: Option<NodeId>,
 }
 
+/// Lazy pre-order traversal, as returned by [`Tree::iter_depth_first`].
+pub struct DfsIter<'a, T> {
+    tree: &'a Tree<T>,
+    stack: Vec<NodeId>,
+}
+
+impl<T> Iterator for DfsIter<'_, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.stack.pop()?;
+        self.stack.extend(self.tree.node(id).children.iter().rev());
+        Some(id)
+    }
+}
+
+impl<T> FusedIterator for DfsIter<'_, T> {}
+
+impl<T> fmt::Debug for DfsIter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DfsIter").field("pending", &self.stack).finish_non_exhaustive()
+    }
+}
+
 impl<T> Tree<T> {
     /// Create an empty tree.
     pub fn new() -> Self {
         Self { nodes: Vec::new(), root: None }
     }
 
+    /// Combine several trees under a new root, in order. Empty trees are
+    /// skipped; ids are reassigned.
+    pub fn from_forest(root_data: T, trees: Vec<Tree<T>>) -> Self {
+        let mut tree = Tree::new();
+        let root = tree.set_root(root_data);
+        for mut other in trees {
+            if let Some(other_root) = other.root.take() {
+                let id = tree.graft_from(&mut other, other_root, Some(root));
+                tree.node_mut(root).children.push(id);
+            }
+        }
+        tree
+    }
+
+    /// Build a tree from a recursive [`NestedNode`], allocating ids in
+    /// pre-order. The inverse of [`Tree::to_nested`].
+    pub fn from_nested(nested: NestedNode<T>) -> Self {
+        let mut tree = Tree::new();
+        let mut stack = vec![(nested, None)];
+        while let Some((NestedNode { value, children }, parent)) = stack.pop() {
+            let id = match parent {
+                Some(p) => tree.add_child(p, value),
+                None => tree.set_root(value),
+            };
+            stack.extend(children.into_iter().rev().map(|c| (c, Some(id))));
+        }
+        tree
+    }
+
+    /// Create an empty tree with room for `capacity` nodes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { nodes: Vec::with_capacity(capacity), root: None }
+    }
+
+    /// Number of nodes the arena can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Number of arena slots left empty by removed nodes. Freed slots are not
+    /// reused, so this only shrinks when the tree is rebuilt, e.g. by
+    /// [`Tree::clone_into`].
+    pub fn free_slots(&self) -> usize {
+        self.nodes.iter().filter(|slot| slot.is_none()).count()
+    }
+
+    /// Get the root node, if any.
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    /// Ids of every live node, in arena order.
+    pub fn node_ids(&self) -> Vec<NodeId> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.is_some())
+            .map(|(i, _)| NodeId(i))
+            .collect()
+    }
+
     /// Create root node.
     pub fn set_root(&mut self, data: T) -> NodeId {
         assert!(self.root.is_none(), "root already exists");
@@ -39,6 +202,13 @@ impl<T> Tree<T> {
         id
     }
 
+    /// Replace the root node's data.
+    pub fn set_root_data(&mut self, data: T) -> Result<(), TreeError> {
+        let root = self.root.ok_or(TreeError::NoRoot)?;
+        self.node_mut(root).data = data;
+        Ok(())
+    }
+
     /// Add a child to a parent.
     pub fn add_child(&mut self, parent: NodeId, data: T) -> NodeId {
         self.assert_exists(parent);
@@ -47,6 +217,99 @@ impl<T> Tree<T> {
         child
     }
 
+    /// Put a new root holding `data` above the current root, which becomes
+    /// its only child. Returns the new root's id.
+    pub fn wrap_root(&mut self, data: T) -> Result<NodeId, TreeError> {
+        let old = self.root.ok_or(TreeError::NoRoot)?;
+        let root = self.alloc(Node { data, parent: None, children: vec![old] });
+        self.node_mut(old).parent = Some(root);
+        self.root = Some(root);
+        Ok(root)
+    }
+
+    /// Add a child to `parent`, inserted so that its children stay sorted by
+    /// `key`. The existing children must already be sorted; a new child goes
+    /// after any siblings with an equal key.
+    pub fn add_child_sorted<K, F>(&mut self, parent: NodeId, data: T, key: F) -> NodeId
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        let k = key(&data);
+        let index = self.node(parent).children.partition_point(|&c| key(&self.node(c).data) <= k);
+        let child = self.alloc(Node { data, parent: Some(parent), children: vec![] });
+        self.node_mut(parent).children.insert(index, child);
+        child
+    }
+
+    /// Insert a new node between `parent` and its child `child`.
+    ///
+    /// The new node takes `child`'s position among `parent`'s children and
+    /// `child` becomes its only child.
+    pub fn insert_between(&mut self, parent: NodeId, child: NodeId, data: T) -> Result<NodeId, TreeError> {
+        let pos = self
+            .node(parent)
+            .children
+            .iter()
+            .position(|&c| c == child)
+            .ok_or(TreeError::NotChild { parent, child })?;
+        let mid = self.alloc(Node { data, parent: Some(parent), children: vec![child] });
+        self.node_mut(parent).children[pos] = mid;
+        self.node_mut(child).parent = Some(mid);
+        Ok(mid)
+    }
+
+    /// Move a node up one level, making it the next sibling of its parent.
+    pub fn promote(&mut self, id: NodeId) -> Result<(), TreeError> {
+        let parent = self.parent(id).ok_or(TreeError::NoGrandparent(id))?;
+        let grandparent = self.parent(parent).ok_or(TreeError::NoGrandparent(id))?;
+
+        self.node_mut(parent).children.retain(|&c| c != id);
+        let siblings = &mut self.node_mut(grandparent).children;
+        let pos = siblings.iter().position(|&c| c == parent).expect("parent is a child of grandparent");
+        siblings.insert(pos + 1, id);
+        self.node_mut(id).parent = Some(grandparent);
+        Ok(())
+    }
+
+    /// Move a node down one level, appending it to the children of one of
+    /// its current siblings.
+    pub fn demote(&mut self, id: NodeId, sibling: NodeId) -> Result<(), TreeError> {
+        let parent = match self.parent(id) {
+            Some(p) if id != sibling && self.parent(sibling) == Some(p) => p,
+            _ => return Err(TreeError::NotSibling { node: id, sibling }),
+        };
+
+        self.node_mut(parent).children.retain(|&c| c != id);
+        self.node_mut(sibling).children.push(id);
+        self.node_mut(id).parent = Some(sibling);
+        Ok(())
+    }
+
+    /// Exchange the positions of two subtrees: each of `a` and `b` moves to
+    /// the other's former parent and child index, taking its descendants
+    /// along. Fails if one of them contains the other, including when they
+    /// are the same node.
+    pub fn swap_subtrees(&mut self, a: NodeId, b: NodeId) -> Result<(), TreeError> {
+        self.assert_exists(b);
+        for (ancestor, descendant) in [(a, b), (b, a)] {
+            if self.path_to(descendant).contains(&ancestor) {
+                return Err(TreeError::Nested { ancestor, descendant });
+            }
+        }
+
+        // Neither is the root, since the root contains every node
+        let pa = self.parent(a).expect("non-root node has a parent");
+        let pb = self.parent(b).expect("non-root node has a parent");
+        let ia = self.rank(a).expect("node is among its parent's children");
+        let ib = self.rank(b).expect("node is among its parent's children");
+        self.node_mut(pa).children[ia] = b;
+        self.node_mut(pb).children[ib] = a;
+        self.node_mut(a).parent = Some(pb);
+        self.node_mut(b).parent = Some(pa);
+        Ok(())
+    }
+
     /// Get immutable reference to node data.
     pub fn get(&self, id: NodeId) -> &T {
         &self.node(id).data
@@ -62,20 +325,312 @@ impl<T> Tree<T> {
         self.node(id).parent
     }
 
+    /// Get the data of a node's ancestors, from its parent up to the root.
+    pub fn ancestors_data(&self, id: NodeId) -> impl Iterator<Item = &T> + '_ {
+        std::iter::successors(self.parent(id), |&p| self.parent(p)).map(|p| &self.node(p).data)
+    }
+
+    /// Number of `parent` hops from `id` up to the root, i.e. its depth.
+    ///
+    /// Runs in O(depth) time by following parent links; nothing is cached.
+    pub fn ancestor_count(&self, id: NodeId) -> usize {
+        std::iter::successors(self.parent(id), |&p| self.parent(p)).count()
+    }
+
+    /// Ids from the root down to and including `id`.
+    pub fn path_to(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path: Vec<NodeId> = std::iter::successors(Some(id), |&n| self.parent(n)).collect();
+        path.reverse();
+        path
+    }
+
+    /// Root-to-node path of the first node, in pre-order, whose data matches `pred`.
+    pub fn find_path_to<F>(&self, mut pred: F) -> Option<Vec<NodeId>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.dfs_depth_iter()
+            .map(|(id, _)| id)
+            .find(|&id| pred(&self.node(id).data))
+            .map(|id| self.path_to(id))
+    }
+
+    /// Ids along one deepest root-to-leaf path; ties go to the first one in
+    /// pre-order. Empty for an empty tree.
+    pub fn longest_path(&self) -> Vec<NodeId> {
+        let mut deepest: Option<(NodeId, usize)> = None;
+        for (id, depth) in self.dfs_depth_iter() {
+            if deepest.is_none_or(|(_, best)| depth > best) {
+                deepest = Some((id, depth));
+            }
+        }
+        deepest.map(|(id, _)| self.path_to(id)).unwrap_or_default()
+    }
+
     /// Get children of a node.
     pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
         self.node(id).children.iter().copied()
     }
 
+    /// Whether a node has no children.
+    pub fn is_leaf(&self, id: NodeId) -> bool {
+        self.node(id).children.is_empty()
+    }
+
+    /// Position of a node among its parent's children, or `None` for the root.
+    pub fn rank(&self, id: NodeId) -> Option<usize> {
+        self.child_index_of(self.parent(id)?, id)
+    }
+
+    /// First direct child of `parent` whose data matches `pred`.
+    pub fn child_matching<F>(&self, parent: NodeId, mut pred: F) -> Option<NodeId>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.node(parent).children.iter().copied().find(|&c| pred(&self.node(c).data))
+    }
+
+    /// Index of `child` among the children of `parent`, or `None` if it is
+    /// not a direct child of `parent`.
+    pub fn child_index_of(&self, parent: NodeId, child: NodeId) -> Option<usize> {
+        self.node(parent).children.iter().position(|&c| c == child)
+    }
+
+    /// Borrow a node's children as a slice, for indexing and `len`.
+    pub fn children_slice(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// Get the data of a node's children.
+    pub fn children_data(&self, id: NodeId) -> impl Iterator<Item = &T> + '_ {
+        self.node(id).children.iter().map(|&c| &self.node(c).data)
+    }
+
+    /// Count the live nodes without children.
+    pub fn count_leaves(&self) -> usize {
+        self.nodes.iter().flatten().filter(|n| n.children.is_empty()).count()
+    }
+
+    /// Count the live nodes whose data satisfies `pred`.
+    pub fn count_matching<F>(&self, mut pred: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.nodes.iter().flatten().filter(|n| pred(&n.data)).count()
+    }
+
+    /// Look for a cycle in the children links, which a well-formed tree
+    /// never has. Returns the ids along the first cycle found, starting from
+    /// the node that is revisited; following `children` from the last id
+    /// leads back to the first.
+    pub fn find_cycle(&self) -> Option<Vec<NodeId>> {
+        const UNSEEN: u8 = 0;
+        const ON_STACK: u8 = 1;
+        const DONE: u8 = 2;
+
+        let mut state = vec![UNSEEN; self.nodes.len()];
+        for start in self.node_ids() {
+            if state[start.0] != UNSEEN {
+                continue;
+            }
+            state[start.0] = ON_STACK;
+            // Each frame is a node and the index of its next child to visit
+            let mut stack = vec![(start, 0)];
+            while let Some(&mut (id, ref mut next)) = stack.last_mut() {
+                let Some(&c) = self.node(id).children.get(*next) else {
+                    state[id.0] = DONE;
+                    stack.pop();
+                    continue;
+                };
+                *next += 1;
+                if self.nodes.get(c.0).is_none_or(Option::is_none) {
+                    continue;
+                }
+                match state[c.0] {
+                    ON_STACK => {
+                        let from = stack.iter().position(|&(n, _)| n == c).expect("node is on the stack");
+                        return Some(stack[from..].iter().map(|&(n, _)| n).collect());
+                    }
+                    UNSEEN => {
+                        state[c.0] = ON_STACK;
+                        stack.push((c, 0));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// Children of every node as a dense list indexed by arena slot: entry
+    /// `i` holds the children of `NodeId(i)`. Freed slots get an empty list,
+    /// like leaves.
+    pub fn as_adjacency_list(&self) -> Vec<Vec<NodeId>> {
+        self.nodes.iter().map(|slot| slot.as_ref().map_or_else(Vec::new, |n| n.children.clone())).collect()
+    }
+
+    /// Whether `other` has the same shape as this tree: the same number of
+    /// children at every position, starting from the root. Data is ignored.
+    pub fn equal_structure<U>(&self, other: &Tree<U>) -> bool {
+        let mut stack = match (self.root, other.root) {
+            (None, None) => return true,
+            (Some(a), Some(b)) => vec![(a, b)],
+            _ => return false,
+        };
+        while let Some((a, b)) = stack.pop() {
+            let (ca, cb) = (&self.node(a).children, &other.node(b).children);
+            if ca.len() != cb.len() {
+                return false;
+            }
+            stack.extend(ca.iter().copied().zip(cb.iter().copied()));
+        }
+        true
+    }
+
+    /// Get the data of every node beneath `id` in pre-order, excluding `id` itself.
+    pub fn descendants_data(&self, id: NodeId) -> impl Iterator<Item = &T> + '_ {
+        DfsIter { tree: self, stack: vec![id] }.skip(1).map(|n| &self.node(n).data)
+    }
+
+    /// Reserve capacity for at least `additional` more children of a node.
+    pub fn reserve_children(&mut self, id: NodeId, additional: usize) {
+        self.node_mut(id).children.reserve(additional);
+    }
+
+    /// Count the nodes beneath a node, excluding the node itself.
+    pub fn count_descendants(&self, id: NodeId) -> usize {
+        let mut count = 0;
+        let mut stack: Vec<NodeId> = self.node(id).children.clone();
+        while let Some(n) = stack.pop() {
+            count += 1;
+            stack.extend(self.node(n).children.iter().copied());
+        }
+        count
+    }
+
+    /// Build a new tree of the same shape by mapping each node's id and data.
+    ///
+    /// Ids are preserved, so a `NodeId` from `self` addresses the same node in
+    /// the result.
+    pub fn map_with_id<U, F>(&self, mut f: F) -> Tree<U>
+    where
+        F: FnMut(NodeId, &T) -> U,
+    {
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, slot)| {
+                slot.as_ref().map(|n| Node {
+                    data: f(NodeId(i), &n.data),
+                    parent: n.parent,
+                    children: n.children.clone(),
+                })
+            })
+            .collect();
+        Tree { nodes, root: self.root }
+    }
+
+    /// Depth of the deepest leaf, or `None` for an empty tree.
+    pub fn deepest_leaf_depth(&self) -> Option<usize> {
+        self.leaf_depths().max()
+    }
+
+    /// Depth of the shallowest leaf, or `None` for an empty tree. Much lower
+    /// than [`Tree::deepest_leaf_depth`] in a lopsided tree.
+    pub fn shallowest_leaf_depth(&self) -> Option<usize> {
+        self.leaf_depths().min()
+    }
+
+    /// Compute depth statistics over all nodes in a single traversal.
+    pub fn depth_stats(&self) -> DepthStats {
+        let mut stats = DepthStats { max: 0, min_leaf: usize::MAX, sum: 0, count: 0, mean: 0.0 };
+        let mut stack: Vec<(NodeId, usize)> = self.root.map(|r| (r, 0)).into_iter().collect();
+        while let Some((id, depth)) = stack.pop() {
+            stats.max = stats.max.max(depth);
+            stats.sum += depth;
+            stats.count += 1;
+            let children = &self.node(id).children;
+            if children.is_empty() {
+                stats.min_leaf = stats.min_leaf.min(depth);
+            }
+            stack.extend(children.iter().map(|&c| (c, depth + 1)));
+        }
+        if stats.count == 0 {
+            stats.min_leaf = 0;
+        } else {
+            stats.mean = stats.sum as f64 / stats.count as f64;
+        }
+        stats
+    }
+
+    /// Fraction of non-leaf nodes that have exactly one child.
+    ///
+    /// Values near 1.0 mean the tree is mostly long chains. Returns 0.0 when
+    /// there are no non-leaf nodes.
+    pub fn single_child_ratio(&self) -> f64 {
+        let (mut inner, mut single) = (0usize, 0usize);
+        for node in self.nodes.iter().flatten() {
+            match node.children.len() {
+                0 => {}
+                1 => {
+                    inner += 1;
+                    single += 1;
+                }
+                _ => inner += 1,
+            }
+        }
+        if inner == 0 { 0.0 } else { single as f64 / inner as f64 }
+    }
+
+    /// Rough number of bytes used by the tree.
+    ///
+    /// Counts the arena's full capacity, each live node's children buffer,
+    /// and whatever heap memory `heap_size` reports for each node's data
+    /// (e.g. `|s: &String| s.capacity()`).
+    pub fn approx_memory_bytes(&self, heap_size: fn(&T) -> usize) -> usize {
+        let arena = std::mem::size_of::<Self>()
+            + self.nodes.capacity() * std::mem::size_of::<Option<Node<T>>>();
+        let per_node: usize = self
+            .nodes
+            .iter()
+            .flatten()
+            .map(|n| n.children.capacity() * std::mem::size_of::<NodeId>() + heap_size(&n.data))
+            .sum();
+        arena + per_node
+    }
+
     /// Depth-first search from root.
     pub fn dfs(&self) -> Vec<NodeId> {
+        self.iter_depth_first().collect()
+    }
+
+    /// Lazily walk the tree in pre-order, visiting nodes only as the
+    /// iterator is advanced.
+    pub fn iter_depth_first(&self) -> DfsIter<'_, T> {
+        DfsIter { tree: self, stack: self.root.into_iter().collect() }
+    }
+
+    /// Depth-first search from root, skipping nodes deeper than `max_depth`
+    /// (the root is at depth 0).
+    pub fn dfs_bounded(&self, max_depth: usize) -> Vec<NodeId> {
         let mut result = Vec::new();
         if let Some(root) = self.root {
-            self.dfs_rec(root, &mut result);
+            self.dfs_bounded_rec(root, 0, max_depth, &mut result);
         }
         result
     }
 
+    /// Lazy depth-first traversal from root yielding each node with its depth.
+    pub fn dfs_depth_iter(&self) -> impl Iterator<Item = (NodeId, usize)> + '_ {
+        let mut stack: Vec<(NodeId, usize)> = self.root.map(|r| (r, 0)).into_iter().collect();
+        std::iter::from_fn(move || {
+            let (id, depth) = stack.pop()?;
+            stack.extend(self.node(id).children.iter().rev().map(|&c| (c, depth + 1)));
+            Some((id, depth))
+        })
+    }
+
     /// Breadth-first search from root.
     pub fn bfs(&self) -> Vec<NodeId> {
         let mut result = Vec::new();
@@ -92,90 +647,1949 @@ impl<T> Tree<T> {
         result
     }
 
-    /// Pretty print tree like `tree` command.
-    pub fn fmt_tree<F>(&self, mut label: F) -> String
+    /// Level-order ids of the nodes at depth `max_depth` or less, counting
+    /// the root as depth 0.
+    pub fn bfs_bounded(&self, max_depth: usize) -> Vec<NodeId> {
+        let mut result = Vec::new();
+        let mut queue: VecDeque<_> = self.root.map(|r| (r, 0)).into_iter().collect();
+        while let Some((id, depth)) = queue.pop_front() {
+            result.push(id);
+            if depth < max_depth {
+                queue.extend(self.node(id).children.iter().map(|&c| (c, depth + 1)));
+            }
+        }
+        result
+    }
+
+    /// In-order traversal from root, treating the first child as the left
+    /// subtree and any later children as the right.
+    ///
+    /// Nodes with more than two children visit the first child, then the
+    /// node, then the remaining children in order.
+    pub fn in_order(&self) -> Vec<NodeId> {
+        let mut result = Vec::new();
+        if let Some(root) = self.root {
+            self.in_order_rec(root, &mut result);
+        }
+        result
+    }
+
+    /// Find every node whose path, as produced by `path_of`, matches the glob
+    /// `pattern`, in pre-order.
+    ///
+    /// A pattern without `/` is matched against the last path component only,
+    /// so `*.rs` finds Rust files at any depth. `path_of` decides what paths
+    /// are relative to; leaving out the root gives `find`-style matching,
+    /// where `src/*.rs` works whatever the root is called. See [`crate::glob`]
+    /// for syntax.
+    pub fn find_by_glob<F>(&self, pattern: &str, path_of: F) -> Vec<NodeId>
     where
-        F: FnMut(&T) -> String,
+        F: Fn(NodeId) -> String,
+    {
+        let basename_only = !pattern.contains('/');
+        self.dfs()
+            .into_iter()
+            .filter(|&id| {
+                let path = path_of(id);
+                let text = if basename_only { path.rsplit('/').next().unwrap_or("") } else { &path };
+                glob_match(pattern, text)
+            })
+            .collect()
+    }
+
+    /// Pre-order walk calling `f` with the root-to-node id path and the data
+    /// of each node. The path is maintained as a stack, so the whole walk is
+    /// O(nodes) rather than rebuilding each path.
+    pub fn walk_paths<F>(&self, mut f: F)
+    where
+        F: FnMut(&[NodeId], &T),
     {
-        let mut out = String::new();
         if let Some(root) = self.root {
-            self.fmt_rec(root, "", true, &mut out, &mut label);
+            let mut path = Vec::new();
+            self.walk_paths_rec(root, &mut path, &mut f);
         }
-        out
     }
 
-    // ===== Internals =====
-
-    fn alloc(&mut self, node: Node<T>) -> NodeId {
-        let id = NodeId(self.nodes.len());
-        self.nodes.push(Some(node));
-        id
+    /// Root-to-node id paths of every node whose data matches `pred`, in pre-order.
+    pub fn paths_matching<F>(&self, mut pred: F) -> Vec<Vec<NodeId>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut out = Vec::new();
+        self.walk_paths(|path, data| {
+            if pred(data) {
+                out.push(path.to_vec());
+            }
+        });
+        out
     }
 
-    fn node(&self, id: NodeId) -> &Node<T> {
-        self.nodes[id.0].as_ref().expect("invalid NodeId")
+    /// Fold over every root-to-leaf path in pre-order, calling `f` with the
+    /// accumulator and the ids along the path.
+    pub fn fold_leaf_paths<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, &[NodeId]) -> B,
+    {
+        let mut acc = Some(init);
+        self.walk_paths(|path, _| {
+            let last = *path.last().expect("walk paths are never empty");
+            if self.is_leaf(last) {
+                acc = acc.take().map(|b| f(b, path));
+            }
+        });
+        acc.expect("accumulator is always put back")
     }
 
-    fn node_mut(&mut self, id: NodeId) -> &mut Node<T> {
-        self.nodes[id.0].as_mut().expect("invalid NodeId")
+    /// Call `f(parent, child)` once for every edge reachable from the root.
+    pub fn each_edge<F>(&self, mut f: F)
+    where
+        F: FnMut(NodeId, NodeId),
+    {
+        for id in self.dfs() {
+            for &child in &self.node(id).children {
+                f(id, child);
+            }
+        }
     }
 
-    fn assert_exists(&self, id: NodeId) {
-        assert!(id.0 < self.nodes.len() && self.nodes[id.0].is_some(), "invalid NodeId");
+    /// Pretty print tree like `tree` command.
+    pub fn fmt_tree<F>(&self, label: F) -> String
+    where
+        F: FnMut(&T) -> String,
+    {
+        let mut out = String::new();
+        self.write_tree(&mut out, label).expect("writing to a String cannot fail");
+        out
     }
 
-    fn dfs_rec(&self, id: NodeId, out: &mut Vec<NodeId>) {
-        out.push(id);
-        for &child in &self.node(id).children {
-            self.dfs_rec(child, out);
+    /// Pretty print tree like `tree` command, writing lines into `w` as they
+    /// are produced rather than building the whole output first.
+    pub fn write_tree<W, F>(&self, w: &mut W, mut label: F) -> fmt::Result
+    where
+        W: fmt::Write,
+        F: FnMut(&T) -> String,
+    {
+        match self.root {
+            Some(root) => self.fmt_rec(root, None, w, &mut label, &Connectors::unicode()),
+            None => Ok(()),
         }
     }
 
-    fn fmt_rec<F>(&self, id: NodeId, prefix: &str, last: bool, out: &mut String, label: &mut F)
+    /// Pretty print tree like `tree` command, drawing with custom connectors.
+    pub fn fmt_tree_connectors<F>(&self, connectors: &Connectors<'_>, mut label: F) -> String
     where
         F: FnMut(&T) -> String,
     {
-        let connector = if prefix.is_empty() { "" }
-                        else if last { "└── " } else { "├── " };
-        out.push_str(prefix);
-        out.push_str(connector);
-        out.push_str(&label(&self.node(id).data));
-        out.push('\n');
-
-        let new_prefix = if prefix.is_empty() {
-            String::new()
-        } else if last {
-            format!("{prefix}    ")
-        } else {
-            format!("{prefix}│   ")
-        };
+        let mut out = String::new();
+        if let Some(root) = self.root {
+            self.fmt_rec(root, None, &mut out, &mut label, connectors)
+                .expect("writing to a String cannot fail");
+        }
+        out
+    }
 
-        let ch = &self.node(id).children;
-        for (i, &c) in ch.iter().enumerate() {
-            let is_last = i + 1 == ch.len();
-            self.fmt_rec(c, &new_prefix, is_last, out, label);
+    /// Like [`Tree::fmt_tree`], but siblings are printed in the order given by
+    /// `cmp`. The tree itself is not reordered.
+    pub fn fmt_tree_sorted<F, C>(&self, mut label: F, mut cmp: C) -> String
+    where
+        F: FnMut(&T) -> String,
+        C: FnMut(&T, &T) -> Ordering,
+    {
+        // Sort a throwaway copy holding each node's id, then label through self
+        let mut view = self.map_with_id(|id, _| id);
+        for id in view.dfs() {
+            view.sort_children_by(id, |&a, &b| cmp(self.get(a), self.get(b)));
         }
+        view.fmt_tree(|&id| label(self.get(id)))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Pretty print the subtree rooted at `id`, with `id` as the top line.
+    pub fn subtree_fmt<F>(&self, id: NodeId, mut label: F) -> String
+    where
+        F: FnMut(&T) -> String,
+    {
+        let mut out = String::new();
+        self.fmt_rec(id, None, &mut out, &mut label, &Connectors::unicode())
+            .expect("writing to a String cannot fail");
+        out
+    }
 
-    #[test]
-    fn build_and_print() {
-        let mut t = Tree::new();
-        let root = t.set_root("root");
-        let a = t.add_child(root, "a");
-        t.add_child(a, "a1");
-        t.add_child(a, "a2");
-        let b = t.add_child(root, "b");
-        t.add_child(b, "b1");
+    /// Export as a Graphviz `digraph`. `node` gives each node's label and
+    /// shape (a Graphviz shape name such as `box` or `ellipse`). Nodes are
+    /// named `n<id>` and listed in pre-order, followed by the edges.
+    pub fn to_dot<F>(&self, mut node: F) -> String
+    where
+        F: FnMut(&T) -> (String, &'static str),
+    {
+        let mut out = String::from("digraph tree {\n");
+        let order = self.dfs();
+        for &id in &order {
+            let (label, shape) = node(&self.node(id).data);
+            out.push_str(&format!("    n{} [label=", id.0));
+            push_dot_string(&mut out, &label);
+            out.push_str(&format!(", shape={shape}];\n"));
+        }
+        self.each_edge(|parent, child| out.push_str(&format!("    n{} -> n{};\n", parent.0, child.0)));
+        out.push_str("}\n");
+        out
+    }
 
-        let dfs_labels: Vec<_> = t.dfs().into_iter().map(|id| t.get(id)).cloned().collect();
-        assert_eq!(dfs_labels, vec!["root", "a", "a1", "a2", "b", "b1"]);
+    /// Export as JSON Lines: one object per node, in pre-order, carrying its
+    /// id, parent id, depth and label.
+    pub fn to_ndjson<F>(&self, mut label: F) -> String
+    where
+        F: FnMut(&T) -> String,
+    {
+        let mut out = String::new();
+        let mut stack: Vec<(NodeId, usize)> = self.root.map(|r| (r, 0)).into_iter().collect();
+        while let Some((id, depth)) = stack.pop() {
+            let node = self.node(id);
+            let parent = node.parent.map_or_else(|| "null".to_string(), |p| p.0.to_string());
+            out.push_str(&format!("{{\"id\":{},\"parent\":{parent},\"depth\":{depth},\"label\":", id.0));
+            push_json_string(&mut out, &label(&node.data));
+            out.push_str("}\n");
+            stack.extend(node.children.iter().rev().map(|&c| (c, depth + 1)));
+        }
+        out
+    }
 
-        println!("{}", t.fmt_tree(|s| s.to_string()));
+    /// Swap the children at positions `i` and `j` of a node.
+    pub fn swap_children(&mut self, id: NodeId, i: usize, j: usize) {
+        let children = &mut self.node_mut(id).children;
+        assert!(i < children.len() && j < children.len(), "child index out of bounds");
+        children.swap(i, j);
     }
-}
 
+    /// Merge children of a node that share a key into the first occurrence.
+    ///
+    /// Children of each duplicate are moved, in order, onto the surviving
+    /// sibling and the duplicate node is freed. Only direct children of `id`
+    /// are deduplicated; merged grandchildren are left as they are.
+    pub fn dedup_children_by_key<K, F>(&mut self, id: NodeId, key: F)
+    where
+        K: Eq + Hash,
+        F: Fn(&T) -> K,
+    {
+        let children = std::mem::take(&mut self.node_mut(id).children);
+        let mut first: HashMap<K, NodeId> = HashMap::new();
+        let mut kept = Vec::with_capacity(children.len());
+        for c in children {
+            match first.entry(key(&self.node(c).data)) {
+                Entry::Occupied(e) => {
+                    let keep = *e.get();
+                    let dup = self.nodes[c.0].take().expect("invalid NodeId");
+                    for &g in &dup.children {
+                        self.node_mut(g).parent = Some(keep);
+                    }
+                    self.node_mut(keep).children.extend(dup.children);
+                }
+                Entry::Vacant(e) => {
+                    e.insert(c);
+                    kept.push(c);
+                }
+            }
+        }
+        self.node_mut(id).children = kept;
+    }
+
+    /// Merge `new_items` into the children of `parent`, both sorted by `key`.
+    ///
+    /// A child whose key matches a new item keeps its id and subtree and is
+    /// passed to `update` along with the item. New items without a match are
+    /// added as leaves, and children without a match are removed with their
+    /// subtrees. The children end up sorted by `key`.
+    pub fn merge_children_sorted<K, F, U>(&mut self, parent: NodeId, new_items: Vec<T>, key: F, update: U)
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+        U: Fn(&mut T, T),
+    {
+        let old = std::mem::take(&mut self.node_mut(parent).children);
+        let mut merged = Vec::with_capacity(new_items.len());
+        let mut old = old.into_iter().peekable();
+        let mut new = new_items.into_iter().peekable();
+        loop {
+            let order = match (old.peek(), new.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(&c), Some(item)) => key(&self.node(c).data).cmp(&key(item)),
+            };
+            match order {
+                Ordering::Less => self.free_subtree(old.next().expect("peeked")),
+                Ordering::Greater => {
+                    let data = new.next().expect("peeked");
+                    merged.push(self.alloc(Node { data, parent: Some(parent), children: vec![] }));
+                }
+                Ordering::Equal => {
+                    let c = old.next().expect("peeked");
+                    update(&mut self.node_mut(c).data, new.next().expect("peeked"));
+                    merged.push(c);
+                }
+            }
+        }
+        self.node_mut(parent).children = merged;
+    }
+
+    /// Replace the order of `id`'s children with `order`, which must hold
+    /// exactly the current children, each once.
+    pub fn set_children_order(&mut self, id: NodeId, order: &[NodeId]) -> Result<(), TreeError> {
+        let mut current = self.node(id).children.clone();
+        let mut wanted = order.to_vec();
+        current.sort_by_key(|c| c.0);
+        wanted.sort_by_key(|c| c.0);
+        if current != wanted {
+            return Err(TreeError::NotPermutation(id));
+        }
+        self.node_mut(id).children = order.to_vec();
+        Ok(())
+    }
+
+    /// Stably sort the children of `id` by comparing their data.
+    pub fn sort_children_by<F>(&mut self, id: NodeId, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut children = std::mem::take(&mut self.node_mut(id).children);
+        children.sort_by(|&a, &b| compare(&self.node(a).data, &self.node(b).data));
+        self.node_mut(id).children = children;
+    }
+
+    /// Remove the children of `id` (and their subtrees) whose data fails `pred`.
+    pub fn retain_children<F>(&mut self, id: NodeId, mut pred: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let children = std::mem::take(&mut self.node_mut(id).children);
+        let mut kept = Vec::with_capacity(children.len());
+        for c in children {
+            if pred(&self.node(c).data) {
+                kept.push(c);
+            } else {
+                self.free_subtree(c);
+            }
+        }
+        self.node_mut(id).children = kept;
+    }
+
+    /// Convert the tree into a recursive [`NestedNode`] structure, mapping
+    /// each node's data with `f`. Nodes are built in post-order. `None` for an
+    /// empty tree.
+    pub fn to_nested<U, F>(&self, mut f: F) -> Option<NestedNode<U>>
+    where
+        F: FnMut(&T) -> U,
+    {
+        self.root.map(|r| self.to_nested_rec(r, &mut f))
+    }
+
+    /// Visit every node in post-order, calling `f` with mutable access to the
+    /// node's data and the (already visited) data of its children.
+    pub fn reduce_in_place<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T, &[&T]),
+    {
+        if let Some(root) = self.root {
+            self.reduce_rec(root, &mut f);
+        }
+    }
+
+    /// Pre-order walk calling `f` with mutable data, the id and the depth of
+    /// each node. `f` picks what happens next; [`WalkAction::SkipChildren`]
+    /// removes the node's subtree below it.
+    pub fn visit_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T, NodeId, usize) -> WalkAction,
+    {
+        let mut stack: Vec<(NodeId, usize)> = self.root.map(|r| (r, 0)).into_iter().collect();
+        while let Some((id, depth)) = stack.pop() {
+            match f(&mut self.node_mut(id).data, id, depth) {
+                WalkAction::Continue => {
+                    stack.extend(self.node(id).children.iter().rev().map(|&c| (c, depth + 1)));
+                }
+                WalkAction::SkipChildren => self.clear_children(id),
+                WalkAction::Stop => return,
+            }
+        }
+    }
+
+    /// Walk the tree top-down, removing the subtree of every node for which
+    /// `f` returns `false`. `f` may edit the data it is given; edits to kept
+    /// nodes stay in place. Descendants of a removed node are not visited.
+    /// If the root is removed the tree is left empty.
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let Some(root) = self.root else { return };
+        if !f(&mut self.node_mut(root).data) {
+            self.root = None;
+            self.nodes.clear();
+            return;
+        }
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let children = std::mem::take(&mut self.node_mut(id).children);
+            let mut kept = Vec::with_capacity(children.len());
+            for c in children {
+                if f(&mut self.node_mut(c).data) {
+                    kept.push(c);
+                } else {
+                    self.free_subtree(c);
+                }
+            }
+            stack.extend(kept.iter().rev().copied());
+            self.node_mut(id).children = kept;
+        }
+    }
+
+    /// Free every child subtree of `id`, leaving `id` itself as a leaf.
+    pub fn clear_children(&mut self, id: NodeId) {
+        for c in std::mem::take(&mut self.node_mut(id).children) {
+            self.free_subtree(c);
+        }
+    }
+
+    /// Drop all children of `id` (and their subtrees) and add `items` as its
+    /// new children, returning their ids in order.
+    pub fn replace_children<I>(&mut self, id: NodeId, items: I) -> Vec<NodeId>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.clear_children(id);
+        items.into_iter().map(|data| self.add_child(id, data)).collect()
+    }
+
+    /// Remove every subtree that contains no node matching `pred`, keeping
+    /// matching nodes, their descendants that match, and the ancestor chain
+    /// leading to each match. The root itself is always kept.
+    pub fn keep_branches_matching<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let order = self.dfs();
+        if order.is_empty() {
+            return;
+        }
+
+        // Walking pre-order backwards settles children before their parent
+        let mut keep = vec![false; self.nodes.len()];
+        for &id in order.iter().rev() {
+            let node = self.node(id);
+            keep[id.0] = pred(&node.data) || node.children.iter().any(|c| keep[c.0]);
+        }
+
+        self.retain_marked(&order, &keep);
+    }
+
+    /// Keep only the nodes within `min..=max` levels of the root (the root
+    /// is level 0), plus whatever shallower nodes are needed to reach them.
+    /// Nodes above `min` with nothing in range beneath them are removed,
+    /// though the root itself is always kept.
+    pub fn retain_depth_range(&mut self, min: usize, max: usize) {
+        let order: Vec<_> = self.dfs_depth_iter().collect();
+        let mut keep = vec![false; self.nodes.len()];
+        for &(id, depth) in order.iter().rev() {
+            keep[id.0] = match depth {
+                d if d > max => false,
+                d if d >= min => true,
+                _ => self.node(id).children.iter().any(|c| keep[c.0]),
+            };
+        }
+        let order: Vec<_> = order.into_iter().map(|(id, _)| id).collect();
+        self.retain_marked(&order, &keep);
+    }
+
+    /// Remove the subtree rooted at `id` and return it as a tree of its own.
+    ///
+    /// The returned tree is compact, with `id`'s data at its root. If `id` is
+    /// the root, `self` is left empty.
+    pub fn split_off(&mut self, id: NodeId) -> Tree<T> {
+        match self.parent(id) {
+            Some(p) => self.node_mut(p).children.retain(|&c| c != id),
+            None => self.root = None,
+        }
+        let mut out = Tree::new();
+        let root = out.graft_from(self, id, None);
+        out.root = Some(root);
+        if self.root.is_none() {
+            self.nodes.clear();
+        }
+        out
+    }
+
+    /// Discard everything outside the subtree of `id`, making `id` the root.
+    ///
+    /// Unlike [`Tree::split_off`] the kept nodes stay in place, so their ids
+    /// are unchanged; the freed slots are left empty.
+    pub fn trim_to_subtree(&mut self, id: NodeId) {
+        let Some(parent) = self.parent(id) else { return };
+        self.node_mut(parent).children.retain(|&c| c != id);
+        self.node_mut(id).parent = None;
+        let old_root = self.root.replace(id).expect("a node with a parent implies a root");
+        self.free_subtree(old_root);
+    }
+
+    /// Replace everything below `id` with the contents of `new`.
+    ///
+    /// `id` itself is kept, so its id and position under its parent are
+    /// unchanged, but it takes on the data of `new`'s root and adopts that
+    /// root's children. The old subtree is freed and the old data returned.
+    pub fn replace_subtree(&mut self, id: NodeId, mut new: Tree<T>) -> T {
+        let new_root = new.root.take().expect("replacement tree has no root");
+        self.clear_children(id);
+
+        let src = new.nodes[new_root.0].take().expect("invalid NodeId");
+        for c in src.children {
+            let child = self.graft_from(&mut new, c, Some(id));
+            self.node_mut(id).children.push(child);
+        }
+        std::mem::replace(&mut self.node_mut(id).data, src.data)
+    }
+
+    /// Move `other` into this tree as the child of `parent` at position `index`.
+    ///
+    /// Returns the new id of `other`'s root; the rest of its nodes get fresh ids.
+    pub fn graft_at_index(&mut self, mut other: Tree<T>, parent: NodeId, index: usize) -> NodeId {
+        assert!(index <= self.node(parent).children.len(), "child index out of bounds");
+        let other_root = other.root.take().expect("grafted tree has no root");
+        let id = self.graft_from(&mut other, other_root, Some(parent));
+        self.node_mut(parent).children.insert(index, id);
+        id
+    }
+
+    /// Merge every node that has exactly one child into that child.
+    ///
+    /// The surviving node keeps its id and gets `join(parent, child)` as data,
+    /// so a chain like `a -> b -> c` becomes a single node.
+    pub fn collapse_chains<F>(&mut self, join: F)
+    where
+        F: Fn(&T, &T) -> T,
+    {
+        let Some(root) = self.root else { return };
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            while let [only] = self.node(id).children[..] {
+                let child = self.nodes[only.0].take().expect("invalid NodeId");
+                for &g in &child.children {
+                    self.node_mut(g).parent = Some(id);
+                }
+                let node = self.node_mut(id);
+                node.data = join(&node.data, &child.data);
+                node.children = child.children;
+            }
+            stack.extend(self.node(id).children.iter().copied());
+        }
+    }
+
+    // ===== Internals =====
+
+    fn alloc(&mut self, node: Node<T>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(node));
+        id
+    }
+
+    fn node(&self, id: NodeId) -> &Node<T> {
+        self.nodes[id.0].as_ref().expect("invalid NodeId")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node<T> {
+        self.nodes[id.0].as_mut().expect("invalid NodeId")
+    }
+
+    fn assert_exists(&self, id: NodeId) {
+        assert!(id.0 < self.nodes.len() && self.nodes[id.0].is_some(), "invalid NodeId");
+    }
+
+    /// Depths of the leaves, in pre-order.
+    fn leaf_depths(&self) -> impl Iterator<Item = usize> + '_ {
+        self.dfs_depth_iter().filter(|&(id, _)| self.is_leaf(id)).map(|(_, depth)| depth)
+    }
+
+    /// Walk `order` (pre-order) and free every child subtree not marked in `keep`.
+    fn retain_marked(&mut self, order: &[NodeId], keep: &[bool]) {
+        for &id in order {
+            if self.nodes[id.0].is_none() {
+                continue;
+            }
+            let (kept, dropped): (Vec<_>, Vec<_>) = self.node(id).children.iter().partition(|c| keep[c.0]);
+            for c in dropped {
+                self.free_subtree(c);
+            }
+            self.node_mut(id).children = kept;
+        }
+    }
+
+    /// Free `id` and all of its descendants. Does not unlink `id` from its parent.
+    fn free_subtree(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(n) = stack.pop() {
+            let node = self.nodes[n.0].take().expect("invalid NodeId");
+            stack.extend(node.children);
+        }
+    }
+
+    /// Move the subtree at `src` out of `other` into this arena under `parent`.
+    /// The caller is responsible for linking the returned id into `parent`.
+    fn graft_from(&mut self, other: &mut Tree<T>, src: NodeId, parent: Option<NodeId>) -> NodeId {
+        let node = other.nodes[src.0].take().expect("invalid NodeId");
+        let id = self.alloc(Node { data: node.data, parent, children: Vec::with_capacity(node.children.len()) });
+        for c in node.children {
+            let child = self.graft_from(other, c, Some(id));
+            self.node_mut(id).children.push(child);
+        }
+        id
+    }
+
+    fn reduce_rec<F>(&mut self, id: NodeId, f: &mut F)
+    where
+        F: FnMut(&mut T, &[&T]),
+    {
+        for c in self.node(id).children.clone() {
+            self.reduce_rec(c, f);
+        }
+        // Lift the node out of its slot so its children can be borrowed alongside it
+        let mut node = self.nodes[id.0].take().expect("invalid NodeId");
+        let children: Vec<&T> = node.children.iter().map(|&c| &self.node(c).data).collect();
+        f(&mut node.data, &children);
+        self.nodes[id.0] = Some(node);
+    }
+
+    fn to_nested_rec<U, F>(&self, id: NodeId, f: &mut F) -> NestedNode<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        let node = self.node(id);
+        let children = node.children.iter().map(|&c| self.to_nested_rec(c, f)).collect();
+        NestedNode { value: f(&node.data), children }
+    }
+
+    fn dfs_bounded_rec(&self, id: NodeId, depth: usize, max_depth: usize, out: &mut Vec<NodeId>) {
+        out.push(id);
+        if depth < max_depth {
+            for &child in &self.node(id).children {
+                self.dfs_bounded_rec(child, depth + 1, max_depth, out);
+            }
+        }
+    }
+
+    fn walk_paths_rec<F>(&self, id: NodeId, path: &mut Vec<NodeId>, f: &mut F)
+    where
+        F: FnMut(&[NodeId], &T),
+    {
+        path.push(id);
+        let node = self.node(id);
+        f(path, &node.data);
+        for &child in &node.children {
+            self.walk_paths_rec(child, path, f);
+        }
+        path.pop();
+    }
+
+    fn in_order_rec(&self, id: NodeId, out: &mut Vec<NodeId>) {
+        let children = &self.node(id).children;
+        match children.split_first() {
+            None => out.push(id),
+            Some((&left, rest)) => {
+                self.in_order_rec(left, out);
+                out.push(id);
+                for &right in rest {
+                    self.in_order_rec(right, out);
+                }
+            }
+        }
+    }
+
+    /// `pos` is `None` for the top line of the output, otherwise the prefix
+    /// inherited from the parent and whether this is its last child.
+    fn fmt_rec<W, F>(&self, id: NodeId, pos: Option<(&str, bool)>, out: &mut W, label: &mut F, conn: &Connectors<'_>) -> fmt::Result
+    where
+        W: fmt::Write,
+        F: FnMut(&T) -> String,
+    {
+        let (prefix, connector, new_prefix) = match pos {
+            None => ("", "", String::new()),
+            Some((prefix, true)) => (prefix, conn.last, format!("{prefix}{}", conn.space)),
+            Some((prefix, false)) => (prefix, conn.branch, format!("{prefix}{}", conn.vertical)),
+        };
+        writeln!(out, "{prefix}{connector}{}", label(&self.node(id).data))?;
+
+        let ch = &self.node(id).children;
+        for (i, &c) in ch.iter().enumerate() {
+            let is_last = i + 1 == ch.len();
+            self.fmt_rec(c, Some((&new_prefix, is_last)), out, label, conn)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone> Tree<T> {
+    /// Overwrite `dst` with a compacted copy of this tree, reusing `dst`'s
+    /// existing allocation where possible.
+    ///
+    /// Nodes are renumbered in pre-order, so ids from `self` are not valid in
+    /// `dst` unless `self` was already compact.
+    pub fn clone_into(&self, dst: &mut Tree<T>) {
+        dst.nodes.clear();
+        dst.root = None;
+        if let Some(root) = self.root {
+            dst.nodes.reserve(self.count_descendants(root) + 1);
+            dst.root = Some(self.clone_rec(root, None, dst));
+        }
+    }
+
+    fn clone_rec(&self, id: NodeId, parent: Option<NodeId>, dst: &mut Tree<T>) -> NodeId {
+        let node = self.node(id);
+        let new = dst.alloc(Node { data: node.data.clone(), parent, children: Vec::with_capacity(node.children.len()) });
+        for &c in &node.children {
+            let child = self.clone_rec(c, Some(new), dst);
+            dst.node_mut(new).children.push(child);
+        }
+        new
+    }
+}
+
+/// Append `s` to `out` as a quoted DOT ID string.
+fn push_dot_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Append `s` to `out` as a quoted JSON string.
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+impl Tree<String> {
+    /// Convert into a tree whose equal labels share a single allocation.
+    ///
+    /// Ids and structure are preserved.
+    pub fn intern(self) -> Tree<Rc<str>> {
+        let mut pool: HashMap<String, Rc<str>> = HashMap::new();
+        let nodes = self
+            .nodes
+            .into_iter()
+            .map(|slot| {
+                slot.map(|n| {
+                    let data = match pool.get(&n.data) {
+                        Some(shared) => Rc::clone(shared),
+                        None => {
+                            let shared: Rc<str> = Rc::from(n.data.as_str());
+                            pool.insert(n.data, Rc::clone(&shared));
+                            shared
+                        }
+                    };
+                    Node { data, parent: n.parent, children: n.children }
+                })
+            })
+            .collect();
+        Tree { nodes, root: self.root }
+    }
+
+    /// Resolve a path of labels to a node, starting with the root's label.
+    pub fn find_by_path(&self, segments: &[&str]) -> Option<NodeId> {
+        let (first, rest) = segments.split_first()?;
+        let root = self.root.filter(|&r| self.get(r) == first)?;
+        rest.iter().try_fold(root, |id, seg| self.children(id).find(|&c| self.get(c) == seg))
+    }
+
+    /// Rewrite every label to its last `sep`-separated component, so a tree
+    /// of full paths prints as basenames. Trailing separators are ignored,
+    /// and a label made only of separators is left as it is.
+    pub fn shrink_labels_to_basename(&mut self, sep: char) {
+        for node in self.nodes.iter_mut().flatten() {
+            let trimmed = node.data.trim_end_matches(sep);
+            if trimmed.is_empty() {
+                continue;
+            }
+            let base = trimmed.rsplit(sep).next().unwrap_or(trimmed);
+            if base.len() != node.data.len() {
+                node.data = base.to_string();
+            }
+        }
+    }
+
+    /// Remove the subtree of every node whose full path, with labels joined
+    /// by `sep`, satisfies `pred`. Descendants of a removed node are not
+    /// checked. If the root's path matches the tree is left empty.
+    pub fn prune_by_path<F>(&mut self, sep: &str, mut pred: F)
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let Some(root) = self.root else { return };
+        let root_path = self.get(root).clone();
+        if pred(&root_path) {
+            self.root = None;
+            self.nodes.clear();
+            return;
+        }
+        let mut stack = vec![(root, root_path)];
+        while let Some((id, path)) = stack.pop() {
+            let children = std::mem::take(&mut self.node_mut(id).children);
+            let mut kept = Vec::with_capacity(children.len());
+            for c in children {
+                let child_path = format!("{path}{sep}{}", self.get(c));
+                if pred(&child_path) {
+                    self.free_subtree(c);
+                } else {
+                    kept.push(c);
+                    stack.push((c, child_path));
+                }
+            }
+            self.node_mut(id).children = kept;
+        }
+    }
+
+    /// The root-to-node path of every node in pre-order, with labels joined by `sep`.
+    pub fn to_paths(&self, sep: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        self.walk_paths(|path, _| {
+            let labels: Vec<&str> = path.iter().map(|&id| self.get(id).as_str()).collect();
+            out.push(labels.join(sep));
+        });
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// root -> (a -> a1, a2), (b -> b1)
+    fn sample() -> (Tree<&'static str>, [NodeId; 6]) {
+        let mut t = Tree::new();
+        let root = t.set_root("root");
+        let a = t.add_child(root, "a");
+        let a1 = t.add_child(a, "a1");
+        let a2 = t.add_child(a, "a2");
+        let b = t.add_child(root, "b");
+        let b1 = t.add_child(b, "b1");
+        (t, [root, a, a1, a2, b, b1])
+    }
+
+    #[test]
+    fn build_and_print() {
+        let mut t = Tree::new();
+        let root = t.set_root("root");
+        let a = t.add_child(root, "a");
+        t.add_child(a, "a1");
+        t.add_child(a, "a2");
+        let b = t.add_child(root, "b");
+        t.add_child(b, "b1");
+
+        let dfs_labels: Vec<_> = t.dfs().into_iter().map(|id| t.get(id)).cloned().collect();
+        assert_eq!(dfs_labels, vec!["root", "a", "a1", "a2", "b", "b1"]);
+
+        println!("{}", t.fmt_tree(|s| s.to_string()));
+    }
+
+    #[test]
+    fn reserve_children_grows_capacity() {
+        let mut t = Tree::new();
+        let root = t.set_root("root");
+        let a = t.add_child(root, "a");
+
+        t.reserve_children(root, 16);
+        assert!(t.node(root).children.capacity() >= 17);
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![a]);
+    }
+
+    #[test]
+    fn collapse_single_child_chains() {
+        let mut t = Tree::new();
+        let a = t.set_root("a".to_string());
+        let b = t.add_child(a, "b".to_string());
+        t.add_child(b, "c".to_string());
+
+        t.collapse_chains(|p, c| format!("{p}/{c}"));
+
+        assert_eq!(t.dfs(), vec![a]);
+        assert_eq!(t.get(a), "a/b/c");
+        assert_eq!(t.fmt_tree(|s| s.clone()), "a/b/c\n");
+    }
+
+    #[test]
+    fn swap_children_reorders_siblings() {
+        let (mut t, [root, a, _, _, b, _]) = sample();
+
+        t.swap_children(root, 0, 1);
+
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![b, a]);
+        let out = t.fmt_tree(|s| s.to_string());
+        assert!(out.find("b1").unwrap() < out.find("a1").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "child index out of bounds")]
+    fn swap_children_rejects_bad_index() {
+        let (mut t, [root, ..]) = sample();
+        t.swap_children(root, 0, 2);
+    }
+
+    #[test]
+    fn count_descendants_of_subtrees() {
+        let (t, [root, a, a1, ..]) = sample();
+        assert_eq!(t.count_descendants(root), 5);
+        assert_eq!(t.count_descendants(a), 2);
+        assert_eq!(t.count_descendants(a1), 0);
+    }
+
+    #[test]
+    fn dedup_children_merges_equal_keys() {
+        let mut t = Tree::new();
+        let root = t.set_root("root");
+        let a = t.add_child(root, "a");
+        let x = t.add_child(a, "x");
+        let b = t.add_child(root, "b");
+        let dup = t.add_child(root, "a");
+        let y = t.add_child(dup, "y");
+
+        t.dedup_children_by_key(root, |s| *s);
+
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![a, b]);
+        assert_eq!(t.children(a).collect::<Vec<_>>(), vec![x, y]);
+        assert_eq!(t.parent(y), Some(a));
+        assert!(t.nodes[dup.0].is_none());
+    }
+
+    #[test]
+    fn map_with_id_preserves_ids() {
+        let (t, [root, a, a1, ..]) = sample();
+
+        let m = t.map_with_id(|id, s| (id, s.to_uppercase()));
+
+        assert_eq!(m.get(a1), &(a1, "A1".to_string()));
+        assert_eq!(m.get(root), &(root, "ROOT".to_string()));
+        assert_eq!(m.children(a).collect::<Vec<_>>(), t.children(a).collect::<Vec<_>>());
+        assert_eq!(m.dfs(), t.dfs());
+    }
+
+    #[test]
+    fn replace_subtree_grafts_new_contents() {
+        let (mut t, [root, a, a1, a2, b, b1]) = sample();
+        let mut new = Tree::new();
+        let n = new.set_root("n");
+        new.add_child(n, "n1");
+
+        let old = t.replace_subtree(a, new);
+
+        assert_eq!(old, "a");
+        let labels: Vec<_> = t.dfs().into_iter().map(|id| *t.get(id)).collect();
+        assert_eq!(labels, vec!["root", "n", "n1", "b", "b1"]);
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![a, b]);
+        let n1 = t.children(a).next().unwrap();
+        assert_eq!(t.parent(n1), Some(a));
+        assert!(t.nodes[a1.0].is_none() && t.nodes[a2.0].is_none());
+        assert_eq!(t.parent(b1), Some(b));
+    }
+
+    #[test]
+    fn each_edge_visits_every_edge_once() {
+        let (t, [root, a, a1, a2, b, b1]) = sample();
+
+        let mut edges = Vec::new();
+        t.each_edge(|p, c| edges.push((p.0, c.0)));
+        edges.sort();
+
+        let mut expected = vec![(root, a), (a, a1), (a, a2), (root, b), (b, b1)]
+            .into_iter()
+            .map(|(p, c)| (p.0, c.0))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(edges, expected);
+    }
+
+    #[test]
+    fn set_root_data_relabels_root() {
+        let mut t = Tree::new();
+        assert_eq!(t.set_root_data("/".to_string()), Err(TreeError::NoRoot));
+
+        let root = t.set_root(String::new());
+        t.add_child(root, "etc".to_string());
+        t.set_root_data("/home/user/project".to_string()).unwrap();
+
+        assert_eq!(t.get(root), "/home/user/project");
+    }
+
+    #[test]
+    fn retain_children_filters_one_level() {
+        let (mut t, [root, a, a1, a2, b, b1]) = sample();
+
+        t.retain_children(root, |s| *s != "a");
+
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![b]);
+        assert_eq!(t.dfs(), vec![root, b, b1]);
+        assert!([a, a1, a2].iter().all(|id| t.nodes[id.0].is_none()));
+    }
+
+    #[test]
+    fn graft_at_index_places_subtree() {
+        let (mut t, [root, a, .., b, _]) = sample();
+        let mut other = Tree::new();
+        let g = other.set_root("g");
+        other.add_child(g, "g1");
+
+        let grafted = t.graft_at_index(other, root, 0);
+
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![grafted, a, b]);
+        assert_eq!(t.parent(grafted), Some(root));
+        let labels: Vec<_> = t.dfs().into_iter().map(|id| *t.get(id)).collect();
+        assert_eq!(labels, vec!["root", "g", "g1", "a", "a1", "a2", "b", "b1"]);
+    }
+
+    #[test]
+    fn in_order_on_binary_tree() {
+        // (1 + (2 * 3))
+        let mut t = Tree::new();
+        let plus = t.set_root("+");
+        t.add_child(plus, "1");
+        let times = t.add_child(plus, "*");
+        t.add_child(times, "2");
+        t.add_child(times, "3");
+
+        let seq: Vec<_> = t.in_order().into_iter().map(|id| *t.get(id)).collect();
+        assert_eq!(seq, vec!["1", "+", "2", "*", "3"]);
+    }
+
+    #[test]
+    fn in_order_with_wide_node() {
+        let mut t = Tree::new();
+        let root = t.set_root("r");
+        t.add_child(root, "x");
+        t.add_child(root, "y");
+        t.add_child(root, "z");
+
+        let seq: Vec<_> = t.in_order().into_iter().map(|id| *t.get(id)).collect();
+        assert_eq!(seq, vec!["x", "r", "y", "z"]);
+    }
+
+    #[test]
+    fn depth_stats_for_sample_tree() {
+        let (mut t, [root, ..]) = sample();
+        t.add_child(root, "c");
+
+        let stats = t.depth_stats();
+
+        assert_eq!(stats.max, 2);
+        assert_eq!(stats.min_leaf, 1);
+        assert_eq!(stats.sum, 9);
+        assert_eq!(stats.count, 7);
+        assert!((stats.mean - 9.0 / 7.0).abs() < f64::EPSILON);
+
+        let empty = Tree::<()>::new().depth_stats();
+        assert_eq!((empty.max, empty.min_leaf, empty.count), (0, 0, 0));
+    }
+
+    #[test]
+    fn children_data_yields_child_values() {
+        let (t, [_, a, ..]) = sample();
+        assert_eq!(t.children_data(a).copied().collect::<Vec<_>>(), vec!["a1", "a2"]);
+    }
+
+    #[test]
+    fn find_by_path_resolves_labels() {
+        let (t, [.., a1, _, _, _]) = sample();
+        let t = t.map_with_id(|_, s| s.to_string());
+
+        assert_eq!(t.find_by_path(&["root", "a", "a1"]), Some(a1));
+        assert_eq!(t.find_by_path(&["root", "a", "b1"]), None);
+        assert_eq!(t.find_by_path(&["a"]), None);
+        assert_eq!(t.find_by_path(&[]), None);
+    }
+
+    #[test]
+    fn fmt_tree_draws_connectors() {
+        let (t, _) = sample();
+        let expected = "\
+root
+├── a
+│   ├── a1
+│   └── a2
+└── b
+    └── b1
+";
+        assert_eq!(t.fmt_tree(|s| s.to_string()), expected);
+    }
+
+    #[test]
+    fn subtree_fmt_starts_at_node() {
+        let (t, [_, a, ..]) = sample();
+        assert_eq!(t.subtree_fmt(a, |s| s.to_string()), "a\n├── a1\n└── a2\n");
+    }
+
+    #[test]
+    fn write_tree_matches_fmt_tree() {
+        let (t, _) = sample();
+        let mut buf = String::new();
+        t.write_tree(&mut buf, |s| s.to_string()).unwrap();
+        assert_eq!(buf, t.fmt_tree(|s| s.to_string()));
+    }
+
+    #[test]
+    fn replace_children_installs_fresh_set() {
+        let (mut t, [root, a, a1, a2, b, b1]) = sample();
+
+        let new = t.replace_children(root, ["x", "y"]);
+
+        assert_eq!(t.children(root).collect::<Vec<_>>(), new);
+        assert_eq!(t.children_data(root).copied().collect::<Vec<_>>(), vec!["x", "y"]);
+        assert!([a, a1, a2, b, b1].iter().all(|id| t.nodes[id.0].is_none()));
+    }
+
+    #[test]
+    fn dfs_bounded_stops_at_depth() {
+        let (t, [root, a, .., b, _]) = sample();
+        assert_eq!(t.dfs_bounded(0), vec![root]);
+        assert_eq!(t.dfs_bounded(1), vec![root, a, b]);
+        assert_eq!(t.dfs_bounded(2), t.dfs());
+    }
+
+    #[test]
+    fn intern_shares_repeated_labels() {
+        let mut t = Tree::new();
+        let root = t.set_root("root".to_string());
+        for _ in 0..3 {
+            let d = t.add_child(root, "src".to_string());
+            t.add_child(d, "mod.rs".to_string());
+        }
+        let before: Vec<String> = t.dfs().into_iter().map(|id| t.get(id).clone()).collect();
+
+        let interned = t.intern();
+
+        let after: Vec<&str> = interned.dfs().into_iter().map(|id| &**interned.get(id)).collect();
+        assert_eq!(after, before);
+
+        let mut ptrs: Vec<*const u8> = interned.dfs().into_iter().map(|id| interned.get(id).as_ptr()).collect();
+        ptrs.sort();
+        ptrs.dedup();
+        assert_eq!(ptrs.len(), 3);
+    }
+
+    #[test]
+    fn is_leaf_checks_children() {
+        let (t, [root, _, a1, ..]) = sample();
+        assert!(t.is_leaf(a1));
+        assert!(!t.is_leaf(root));
+    }
+
+    #[test]
+    fn ancestors_data_walks_to_root() {
+        let (t, [root, _, a1, ..]) = sample();
+        assert_eq!(t.ancestors_data(a1).copied().collect::<Vec<_>>(), vec!["a", "root"]);
+        assert_eq!(t.ancestors_data(root).count(), 0);
+    }
+
+    #[test]
+    fn find_by_glob_matches_paths() {
+        let mut t = Tree::new();
+        let root = t.set_root("proj".to_string());
+        let src = t.add_child(root, "src".to_string());
+        let main = t.add_child(src, "main.rs".to_string());
+        t.add_child(src, "notes.txt".to_string());
+        let build = t.add_child(root, "build.rs".to_string());
+        let path_of = |id| {
+            let mut parts: Vec<&str> = t.ancestors_data(id).map(String::as_str).collect();
+            parts.reverse();
+            parts.push(t.get(id));
+            parts.join("/")
+        };
+
+        assert_eq!(t.find_by_glob("*.rs", path_of), vec![main, build]);
+        assert_eq!(t.find_by_glob("proj/src/*", path_of).len(), 2);
+        assert_eq!(t.find_by_glob("**/src/?ain.rs", path_of), vec![main]);
+    }
+
+    #[test]
+    fn insert_between_splices_node() {
+        let (mut t, [root, a, a1, _, b, _]) = sample();
+
+        let g = t.insert_between(root, a, "group").unwrap();
+
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![g, b]);
+        assert_eq!(t.children(g).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(t.parent(a), Some(g));
+        assert_eq!(t.ancestors_data(a1).copied().collect::<Vec<_>>(), vec!["a", "group", "root"]);
+    }
+
+    #[test]
+    fn insert_between_rejects_non_child() {
+        let (mut t, [root, a, a1, ..]) = sample();
+        let before = t.node_ids().len();
+
+        assert_eq!(t.insert_between(root, a1, "x"), Err(TreeError::NotChild { parent: root, child: a1 }));
+        assert_eq!(t.node_ids().len(), before);
+        assert_eq!(t.parent(a1), Some(a));
+    }
+
+    #[test]
+    fn to_ndjson_emits_one_line_per_node() {
+        let (t, _) = sample();
+        let expected = r#"{"id":0,"parent":null,"depth":0,"label":"root"}
+{"id":1,"parent":0,"depth":1,"label":"a"}
+{"id":2,"parent":1,"depth":2,"label":"a1"}
+{"id":3,"parent":1,"depth":2,"label":"a2"}
+{"id":4,"parent":0,"depth":1,"label":"b"}
+{"id":5,"parent":4,"depth":2,"label":"b1"}
+"#;
+        assert_eq!(t.to_ndjson(|s| s.to_string()), expected);
+    }
+
+    #[test]
+    fn to_ndjson_escapes_labels() {
+        let mut t = Tree::new();
+        t.set_root("say \"hi\"\\\n");
+        assert_eq!(
+            t.to_ndjson(|s| s.to_string()),
+            concat!(r#"{"id":0,"parent":null,"depth":0,"label":"say \"hi\"\\\n"}"#, "\n")
+        );
+    }
+
+    #[test]
+    fn single_child_ratio_chain_vs_bushy() {
+        let mut chain = Tree::new();
+        let mut id = chain.set_root(0);
+        for i in 1..5 {
+            id = chain.add_child(id, i);
+        }
+        assert_eq!(chain.single_child_ratio(), 1.0);
+
+        let (bushy, _) = sample();
+        // root and a are bushy, b has a single child
+        assert!((bushy.single_child_ratio() - 1.0 / 3.0).abs() < f64::EPSILON);
+
+        assert_eq!(Tree::<()>::new().single_child_ratio(), 0.0);
+    }
+
+    #[test]
+    fn clone_into_copies_compacted_tree() {
+        let (mut src, [root, a, ..]) = sample();
+        src.retain_children(a, |s| *s != "a1");
+
+        let mut dst = Tree::new();
+        let junk = dst.set_root("junk");
+        for _ in 0..32 {
+            dst.add_child(junk, "x");
+        }
+        let cap = dst.nodes.capacity();
+
+        src.clone_into(&mut dst);
+
+        assert_eq!(dst.fmt_tree(|s| s.to_string()), src.fmt_tree(|s| s.to_string()));
+        assert_eq!(dst.nodes.len(), 1 + src.count_descendants(root));
+        assert!(dst.nodes.iter().all(Option::is_some));
+        assert_eq!(dst.nodes.capacity(), cap);
+    }
+
+    #[test]
+    fn keep_branches_matching_prunes_unrelated() {
+        let mut t = Tree::new();
+        let root = t.set_root("proj");
+        let src = t.add_child(root, "src");
+        let main = t.add_child(src, "main.rs");
+        t.add_child(src, "notes.txt");
+        let docs = t.add_child(root, "docs");
+        t.add_child(docs, "guide.md");
+        let tools = t.add_child(root, "tools");
+        let codegen = t.add_child(tools, "codegen");
+        let gen_rs = t.add_child(codegen, "gen.rs");
+
+        t.keep_branches_matching(|s| s.ends_with(".rs"));
+
+        assert_eq!(t.dfs(), vec![root, src, main, tools, codegen, gen_rs]);
+        assert!(t.nodes[docs.0].is_none());
+    }
+
+    #[test]
+    fn node_ids_skips_freed_slots() {
+        let (mut t, [root, a, a1, a2, b, b1]) = sample();
+        assert_eq!(t.node_ids(), vec![root, a, a1, a2, b, b1]);
+
+        t.retain_children(a, |s| *s != "a1");
+
+        assert_eq!(t.node_ids(), vec![root, a, a2, b, b1]);
+    }
+
+    #[test]
+    fn fmt_tree_with_custom_connectors() {
+        let (t, _) = sample();
+        let leafy = Connectors { branch: "🌿", last: "🍂", vertical: "🌲", space: "  " };
+        let expected = "\
+root
+🌿a
+🌲🌿a1
+🌲🍂a2
+🍂b
+  🍂b1
+";
+        assert_eq!(t.fmt_tree_connectors(&leafy, |s| s.to_string()), expected);
+        assert_eq!(
+            t.fmt_tree_connectors(&Connectors::unicode(), |s| s.to_string()),
+            t.fmt_tree(|s| s.to_string())
+        );
+        assert!(t.fmt_tree_connectors(&Connectors::ascii(), |s| s.to_string()).contains("`-- b1"));
+    }
+
+    #[test]
+    fn promote_moves_node_up() {
+        let (mut t, [root, a, a1, a2, b, _]) = sample();
+
+        t.promote(a1).unwrap();
+
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![a, a1, b]);
+        assert_eq!(t.children(a).collect::<Vec<_>>(), vec![a2]);
+        assert_eq!(t.parent(a1), Some(root));
+
+        assert_eq!(t.promote(a), Err(TreeError::NoGrandparent(a)));
+        assert_eq!(t.promote(root), Err(TreeError::NoGrandparent(root)));
+    }
+
+    #[test]
+    fn demote_nests_under_sibling() {
+        let (mut t, [root, a, a1, a2, b, _]) = sample();
+
+        t.demote(a2, a1).unwrap();
+
+        assert_eq!(t.children(a).collect::<Vec<_>>(), vec![a1]);
+        assert_eq!(t.children(a1).collect::<Vec<_>>(), vec![a2]);
+        assert_eq!(t.parent(a2), Some(a1));
+
+        assert_eq!(t.demote(a1, b), Err(TreeError::NotSibling { node: a1, sibling: b }));
+        assert_eq!(t.demote(a, a), Err(TreeError::NotSibling { node: a, sibling: a }));
+        assert!(t.demote(root, a).is_err());
+    }
+
+    #[test]
+    fn approx_memory_grows_with_labels() {
+        let (t, _) = sample();
+        let t = t.map_with_id(|_, s| s.to_string());
+        let before = t.approx_memory_bytes(|s| s.capacity());
+
+        let grown = t.map_with_id(|_, s| s.repeat(100));
+
+        assert!(grown.approx_memory_bytes(|s| s.capacity()) > before);
+        assert!(before > t.approx_memory_bytes(|_| 0));
+    }
+
+    #[test]
+    fn dfs_depth_iter_matches_dfs() {
+        let (t, _) = sample();
+        let reference: Vec<_> = t.dfs().into_iter().map(|id| (id, t.ancestors_data(id).count())).collect();
+        assert_eq!(t.dfs_depth_iter().collect::<Vec<_>>(), reference);
+        assert_eq!(Tree::<()>::new().dfs_depth_iter().count(), 0);
+    }
+
+    #[test]
+    fn split_off_subtree() {
+        let (mut t, [root, a, .., b, b1]) = sample();
+
+        let sub = t.split_off(a);
+
+        assert_eq!(sub.fmt_tree(|s| s.to_string()), "a\n├── a1\n└── a2\n");
+        assert_eq!(sub.parent(sub.root().unwrap()), None);
+        assert_eq!(t.dfs(), vec![root, b, b1]);
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![b]);
+        assert_eq!(t.node_ids(), vec![root, b, b1]);
+    }
+
+    #[test]
+    fn split_off_root_empties_tree() {
+        let (mut t, [root, ..]) = sample();
+        let before = t.fmt_tree(|s| s.to_string());
+
+        let whole = t.split_off(root);
+
+        assert_eq!(whole.fmt_tree(|s| s.to_string()), before);
+        assert_eq!(t.root(), None);
+        assert!(t.node_ids().is_empty());
+    }
+
+    #[test]
+    fn find_path_to_reveals_match() {
+        let (t, [root, .., b, b1]) = sample();
+        assert_eq!(t.find_path_to(|s| *s == "b1"), Some(vec![root, b, b1]));
+        assert_eq!(t.find_path_to(|s| *s == "root"), Some(vec![root]));
+        assert_eq!(t.find_path_to(|s| *s == "zzz"), None);
+    }
+
+    #[test]
+    fn clear_children_keeps_node() {
+        let (mut t, [root, a, a1, a2, b, b1]) = sample();
+
+        t.clear_children(a);
+
+        assert!(t.is_leaf(a));
+        assert_eq!(t.get(a), &"a");
+        assert_eq!(t.node_ids(), vec![root, a, b, b1]);
+        assert!(t.nodes[a1.0].is_none() && t.nodes[a2.0].is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn node_id_serializes_as_integer() {
+        let json = serde_json::to_string(&NodeId(5)).unwrap();
+        assert_eq!(json, "5");
+        assert_eq!(serde_json::from_str::<NodeId>(&json).unwrap(), NodeId(5));
+    }
+
+    #[test]
+    fn walk_paths_matches_path_to() {
+        let (t, _) = sample();
+        let mut seen = Vec::new();
+        t.walk_paths(|path, data| seen.push((path.to_vec(), *data)));
+
+        let expected: Vec<_> = t.dfs().into_iter().map(|id| (t.path_to(id), *t.get(id))).collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn longest_path_picks_deepest_branch() {
+        let (mut t, [root, .., b, b1]) = sample();
+        let b2 = t.add_child(b1, "b2");
+
+        assert_eq!(t.longest_path(), vec![root, b, b1, b2]);
+
+        let (ties, [root, a, a1, ..]) = sample();
+        assert_eq!(ties.longest_path(), vec![root, a, a1]);
+        assert!(Tree::<()>::new().longest_path().is_empty());
+    }
+
+    #[test]
+    fn with_capacity_presizes_arena() {
+        let mut t = Tree::with_capacity(6);
+        let root = t.set_root(0);
+        for i in 1..6 {
+            t.add_child(root, i);
+        }
+        assert_eq!(t.nodes.capacity(), 6);
+    }
+
+    #[test]
+    fn from_forest_joins_trees_under_root() {
+        let mut c = Tree::new();
+        let c_root = c.set_root("C:");
+        c.add_child(c_root, "Windows");
+        let mut d = Tree::new();
+        d.set_root("D:");
+
+        let t = Tree::from_forest("drives", vec![c, Tree::new(), d]);
+
+        assert_eq!(t.fmt_tree(|s| s.to_string()), "drives\n├── C:\n│   └── Windows\n└── D:\n");
+        let root = t.root().unwrap();
+        assert!(t.children(root).all(|c| t.parent(c) == Some(root)));
+    }
+
+    #[test]
+    fn rank_is_index_among_siblings() {
+        let (t, [root, a, _, a2, b, _]) = sample();
+        assert_eq!(t.rank(a2), Some(1));
+        assert_eq!(t.rank(a), Some(0));
+        assert_eq!(t.rank(b), Some(1));
+        assert_eq!(t.rank(root), None);
+    }
+
+    #[test]
+    fn ancestor_count_follows_parent_links() {
+        let (t, [root, a, a1, _, _, b1]) = sample();
+        assert_eq!(t.ancestor_count(a1), 2);
+        assert_eq!(t.ancestor_count(b1), 2);
+        assert_eq!(t.ancestor_count(a), 1);
+        assert_eq!(t.ancestor_count(root), 0);
+    }
+
+    #[test]
+    fn retain_mut_edits_kept_nodes() {
+        let mut t = Tree::new();
+        let root = t.set_root("root".to_string());
+        let a = t.add_child(root, "a".to_string());
+        t.add_child(a, "a1".to_string());
+        t.add_child(a, "skip".to_string());
+        let skipped = t.add_child(root, "skip-b".to_string());
+        t.add_child(skipped, "b1".to_string());
+
+        let mut seen = Vec::new();
+        t.retain_mut(|s| {
+            seen.push(s.clone());
+            if s.starts_with("skip") {
+                return false;
+            }
+            *s = s.to_uppercase();
+            true
+        });
+        assert_eq!(t.fmt_tree(|s| s.clone()), "ROOT\n└── A\n    └── A1\n");
+        assert!(!seen.contains(&"b1".to_string()));
+
+        t.retain_mut(|_| false);
+        assert_eq!(t.root(), None);
+    }
+
+    #[test]
+    fn equal_structure_ignores_data() {
+        let (t, _) = sample();
+        let sizes = t.map_with_id(|_, s| s.len() as i32);
+        let names = t.map_with_id(|_, s| s.to_string());
+        assert!(sizes.equal_structure(&names));
+
+        let mut other = t.map_with_id(|_, s| s.to_string());
+        let leaf = other.find_by_path(&["root", "a", "a1"]).unwrap();
+        other.add_child(leaf, "extra".to_string());
+        assert!(!sizes.equal_structure(&other));
+        assert!(!sizes.equal_structure(&Tree::<String>::new()));
+        assert!(Tree::<i32>::new().equal_structure(&Tree::<String>::new()));
+    }
+
+    #[test]
+    fn to_nested_mirrors_shape() {
+        let (t, _) = sample();
+        let mut order = Vec::new();
+        let nested = t.to_nested(|s| {
+            order.push(*s);
+            s.to_uppercase()
+        });
+
+        let Some(NestedNode { value, children }) = nested else { panic!("empty") };
+        assert_eq!(value, "ROOT");
+        match children.as_slice() {
+            [a, b] => {
+                assert_eq!(a.value, "A");
+                assert_eq!(a.children.iter().map(|c| c.value.as_str()).collect::<Vec<_>>(), ["A1", "A2"]);
+                assert_eq!(b.children, [NestedNode { value: "B1".to_string(), children: vec![] }]);
+            }
+            other => panic!("unexpected children: {other:?}"),
+        }
+        assert_eq!(order, ["a1", "a2", "a", "b1", "b", "root"]);
+        assert!(Tree::<u8>::new().to_nested(|&x| x).is_none());
+    }
+
+    #[test]
+    fn from_nested_round_trips() {
+        let leaf = |value| NestedNode { value, children: vec![] };
+        let nested = NestedNode {
+            value: "root",
+            children: vec![
+                NestedNode { value: "a", children: vec![leaf("a1"), leaf("a2")] },
+                NestedNode { value: "b", children: vec![leaf("b1")] },
+            ],
+        };
+
+        let t = Tree::from_nested(nested.clone());
+        assert_eq!(t.fmt_tree(|s| s.to_string()), "root\n├── a\n│   ├── a1\n│   └── a2\n└── b\n    └── b1\n");
+        assert_eq!(t.to_nested(|&s| s), Some(nested));
+        assert_eq!(t, sample().0);
+    }
+
+    #[test]
+    fn sort_children_by_is_stable() {
+        let mut t = Tree::new();
+        let root = t.set_root("root");
+        for name in ["bb", "a", "cc", "d"] {
+            t.add_child(root, name);
+        }
+        t.sort_children_by(root, |x, y| y.len().cmp(&x.len()));
+        assert_eq!(t.children_data(root).copied().collect::<Vec<_>>(), ["bb", "cc", "a", "d"]);
+    }
+
+    #[test]
+    fn count_matching_skips_freed_slots() {
+        let (mut t, [_, _, _, _, b, _]) = sample();
+        assert_eq!(t.count_matching(|s| s.starts_with('a')), 3);
+        t.split_off(b);
+        assert_eq!(t.count_matching(|s| s.starts_with('b')), 0);
+        assert_eq!(t.count_matching(|_| true), 4);
+    }
+
+    #[test]
+    fn fmt_tree_sorted_leaves_order_alone() {
+        let (t, _) = sample();
+        let before = t.dfs();
+
+        let out = t.fmt_tree_sorted(|s| s.to_string(), |a, b| b.cmp(a));
+
+        assert_eq!(out, "root\n├── b\n│   └── b1\n└── a\n    ├── a2\n    └── a1\n");
+        assert_eq!(t.dfs(), before);
+    }
+
+    #[test]
+    fn add_child_sorted_keeps_order() {
+        let mut t = Tree::new();
+        let root = t.set_root((0, ""));
+        for item in [(3, "c"), (1, "a"), (4, "d"), (2, "b"), (1, "a2"), (0, "z")] {
+            t.add_child_sorted(root, item, |&(k, _)| k);
+        }
+        let names: Vec<_> = t.children_data(root).map(|&(_, n)| n).collect();
+        assert_eq!(names, ["z", "a", "a2", "b", "c", "d"]);
+        assert!(t.children(root).all(|c| t.parent(c) == Some(root)));
+    }
+
+    #[test]
+    fn bfs_bounded_stops_at_depth() {
+        let (t, [root, a, a1, a2, b, b1]) = sample();
+        assert_eq!(t.bfs_bounded(0), vec![root]);
+        assert_eq!(t.bfs_bounded(1), vec![root, a, b]);
+        assert_eq!(t.bfs_bounded(5), vec![root, a, b, a1, a2, b1]);
+        assert!(Tree::<u8>::new().bfs_bounded(3).is_empty());
+    }
+
+    #[test]
+    fn find_cycle_reports_cycle_path() {
+        let (mut t, [root, a, a1, ..]) = sample();
+        assert_eq!(t.find_cycle(), None);
+
+        // Corrupt the arena directly: a1 now lists its grandparent as a child
+        t.node_mut(a1).children.push(root);
+        assert_eq!(t.find_cycle(), Some(vec![root, a, a1]));
+    }
+
+    #[test]
+    fn reduce_in_place_sees_reduced_children() {
+        let (t, _) = sample();
+        let mut t = t.map_with_id(|_, s| s.to_string());
+
+        t.reduce_in_place(|label, children| {
+            if !children.is_empty() {
+                label.push_str(&format!(" ({})", children.len()));
+            }
+        });
+        assert_eq!(t.fmt_tree(|s| s.clone()), "root (2)\n├── a (2)\n│   ├── a1\n│   └── a2\n└── b (1)\n    └── b1\n");
+
+        // Children are finished before their parent sees them
+        let mut sizes = t.map_with_id(|_, _| 1);
+        sizes.reduce_in_place(|n, children| *n += children.iter().copied().sum::<i32>());
+        assert_eq!(*sizes.get(sizes.root().unwrap()), 6);
+    }
+
+    #[test]
+    fn to_paths_lists_every_node() {
+        let (t, _) = sample();
+        let t = t.map_with_id(|_, s| s.to_string());
+        assert_eq!(t.to_paths("/"), ["root", "root/a", "root/a/a1", "root/a/a2", "root/b", "root/b/b1"]);
+        assert!(Tree::<String>::new().to_paths("/").is_empty());
+    }
+
+    #[test]
+    fn free_slots_track_removed_nodes() {
+        let (mut t, [_, a, ..]) = sample();
+        assert_eq!(t.free_slots(), 0);
+        assert!(t.capacity() >= 6);
+
+        t.clear_children(a);
+        assert_eq!(t.free_slots(), 2);
+
+        let mut compact = Tree::with_capacity(16);
+        t.clone_into(&mut compact);
+        assert_eq!(compact.free_slots(), 0);
+        assert!(compact.capacity() >= 16);
+    }
+
+    #[test]
+    fn iter_depth_first_is_lazy() {
+        let (t, [root, a, a1, a2, b, b1]) = sample();
+        let mut it = t.iter_depth_first();
+
+        assert_eq!(it.by_ref().take(3).collect::<Vec<_>>(), vec![root, a, a1]);
+        // Resumes where it stopped rather than starting over
+        assert_eq!(it.next(), Some(a2));
+        assert_eq!(it.collect::<Vec<_>>(), vec![b, b1]);
+
+        // Stopping early visits nothing past the match
+        let mut visited = 0;
+        let found = t.iter_depth_first().inspect(|_| visited += 1).find(|&id| id == a1);
+        assert_eq!(found, Some(a1));
+        assert_eq!(visited, 3);
+
+        let mut it = t.iter_depth_first();
+        it.by_ref().for_each(drop);
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next(), None);
+        assert!(format!("{it:?}").starts_with("DfsIter"));
+        assert_eq!(t.iter_depth_first().collect::<Vec<_>>(), t.dfs());
+    }
+
+    #[test]
+    fn swap_subtrees_exchanges_positions() {
+        let (mut t, [root, a, a1, a2, b, b1]) = sample();
+
+        t.swap_subtrees(a1, b).unwrap();
+        assert_eq!(t.fmt_tree(|s| s.to_string()), "root\n├── a\n│   ├── b\n│   │   └── b1\n│   └── a2\n└── a1\n");
+        assert_eq!(t.parent(b), Some(a));
+        assert_eq!(t.parent(a1), Some(root));
+
+        t.swap_subtrees(b, a2).unwrap();
+        assert_eq!(t.children(a).collect::<Vec<_>>(), vec![a2, b]);
+
+        assert_eq!(t.swap_subtrees(a, b1), Err(TreeError::Nested { ancestor: a, descendant: b1 }));
+        assert_eq!(t.swap_subtrees(root, a1), Err(TreeError::Nested { ancestor: root, descendant: a1 }));
+        assert!(t.swap_subtrees(a1, a1).is_err());
+    }
+
+    #[test]
+    fn child_index_of_requires_direct_child() {
+        let (t, [root, a, a1, a2, b, _]) = sample();
+        assert_eq!(t.child_index_of(a, a2), Some(1));
+        assert_eq!(t.child_index_of(root, b), Some(1));
+        assert_eq!(t.child_index_of(root, a1), None);
+        assert_eq!(t.child_index_of(b, a), None);
+    }
+
+    #[test]
+    fn descendants_data_excludes_self() {
+        let (t, [root, a, _, _, _, b1]) = sample();
+        assert_eq!(t.descendants_data(a).copied().collect::<Vec<_>>(), ["a1", "a2"]);
+        assert_eq!(t.descendants_data(root).count(), 5);
+        assert_eq!(t.descendants_data(b1).next(), None);
+    }
+
+    #[test]
+    fn fold_leaf_paths_visits_each_leaf_path() {
+        let (t, [root, _, _, _, b, b1]) = sample();
+        assert_eq!(t.fold_leaf_paths(0, |n, _| n + 1), 3);
+        assert_eq!(t.fold_leaf_paths(0, |longest, path| longest.max(path.len())), 3);
+        assert_eq!(t.fold_leaf_paths(Vec::new(), |mut v, path| {
+            v.push(path.to_vec());
+            v
+        }).last(), Some(&vec![root, b, b1]));
+    }
+
+    #[test]
+    fn retain_depth_range_keeps_band_and_scaffolding() {
+        let mut t = Tree::new();
+        let root = t.set_root("root");
+        let a = t.add_child(root, "a");
+        let a1 = t.add_child(a, "a1");
+        t.add_child(a1, "a1x");
+        t.add_child(root, "b");
+
+        let mut band = Tree::from_nested(t.to_nested(|&s| s).unwrap());
+        band.retain_depth_range(1, 2);
+        assert_eq!(band.fmt_tree(|s| s.to_string()), "root\n├── a\n│   └── a1\n└── b\n");
+
+        t.retain_depth_range(2, 3);
+        assert_eq!(t.fmt_tree(|s| s.to_string()), "root\n└── a\n    └── a1\n        └── a1x\n");
+    }
+
+    #[test]
+    fn shrink_labels_to_basename_strips_parents() {
+        let (t, _) = sample();
+        let mut t = t.map_with_id(|id, _| t.path_to(id).iter().map(|&n| *t.get(n)).collect::<Vec<_>>().join("/"));
+        t.set_root_data("/srv/root/".to_string()).unwrap();
+
+        t.shrink_labels_to_basename('/');
+        assert_eq!(t.fmt_tree(|s| s.clone()), "root\n├── a\n│   ├── a1\n│   └── a2\n└── b\n    └── b1\n");
+
+        let mut slash = Tree::new();
+        slash.set_root("/".to_string());
+        slash.shrink_labels_to_basename('/');
+        assert_eq!(slash.fmt_tree(|s| s.clone()), "/\n");
+    }
+
+    #[test]
+    fn merge_children_sorted_updates_inserts_and_removes() {
+        let mut t = Tree::new();
+        let root = t.set_root(("root", 0));
+        let b = t.add_child(root, ("b", 1));
+        t.add_child(b, ("b-inner", 1));
+        t.add_child(root, ("d", 1));
+        let f = t.add_child(root, ("f", 1));
+
+        t.merge_children_sorted(root, vec![("a", 2), ("b", 2), ("e", 2), ("f", 2)], |&(k, _)| k, |old, new| old.1 += new.1);
+
+        let children: Vec<_> = t.children_data(root).copied().collect();
+        assert_eq!(children, [("a", 2), ("b", 3), ("e", 2), ("f", 3)]);
+        assert_eq!(t.children(root).nth(1), Some(b));
+        assert_eq!(t.children(root).nth(3), Some(f));
+        assert_eq!(t.children_data(b).count(), 1);
+        assert_eq!(t.count_matching(|&(k, _)| k == "d"), 0);
+    }
+
+    #[test]
+    fn leaf_depths_of_lopsided_tree() {
+        let mut t = Tree::new();
+        let root = t.set_root(0);
+        t.add_child(root, 1);
+        let mut deep = t.add_child(root, 1);
+        for d in 2..6 {
+            deep = t.add_child(deep, d);
+        }
+        assert_eq!(t.deepest_leaf_depth(), Some(5));
+        assert_eq!(t.shallowest_leaf_depth(), Some(1));
+
+        let single = Tree::from_nested(NestedNode { value: 0, children: vec![] });
+        assert_eq!(single.shallowest_leaf_depth(), Some(0));
+        assert_eq!(Tree::<u8>::new().deepest_leaf_depth(), None);
+    }
+
+    #[test]
+    fn visit_mut_edits_and_prunes() {
+        let (t, [_, _, _, _, b, _]) = sample();
+        let mut t = t.map_with_id(|_, s| s.to_string());
+
+        t.visit_mut(|label, _, depth| {
+            let action = if label == "a" { WalkAction::SkipChildren } else { WalkAction::Continue };
+            label.push_str(&format!("@{depth}"));
+            action
+        });
+        assert_eq!(t.fmt_tree(|s| s.clone()), "root@0\n├── a@1\n└── b@1\n    └── b1@2\n");
+
+        let mut seen = Vec::new();
+        t.visit_mut(|_, id, _| {
+            seen.push(id);
+            if id == b { WalkAction::Stop } else { WalkAction::Continue }
+        });
+        assert_eq!(seen.len(), 3);
+    }
+
+    #[test]
+    fn child_matching_checks_direct_children_only() {
+        let (t, [root, a, a1, ..]) = sample();
+        assert_eq!(t.child_matching(a, |&s| s == "a1"), Some(a1));
+        assert_eq!(t.child_matching(root, |&s| s == "a1"), None);
+        assert_eq!(t.child_matching(a, |&s| s == "zz"), None);
+    }
+
+    #[test]
+    fn trim_to_subtree_keeps_ids() {
+        let (mut t, [_, a, a1, a2, ..]) = sample();
+        t.trim_to_subtree(a);
+        assert_eq!(t.root(), Some(a));
+        assert_eq!(t.parent(a), None);
+        assert_eq!(t.node_ids(), vec![a, a1, a2]);
+        assert_eq!(t.fmt_tree(|s| s.to_string()), "a\n├── a1\n└── a2\n");
+
+        t.trim_to_subtree(a);
+        assert_eq!(t.node_ids().len(), 3);
+    }
+
+    #[test]
+    fn paths_matching_includes_context() {
+        let (t, [root, a, a1, a2, ..]) = sample();
+        assert_eq!(t.paths_matching(|s| s.starts_with('a')), vec![vec![root, a], vec![root, a, a1], vec![root, a, a2]]);
+        assert!(t.paths_matching(|s| s.is_empty()).is_empty());
+    }
+
+    #[test]
+    fn set_children_order_reorders() {
+        let (mut t, [root, a, _, _, b, _]) = sample();
+        t.set_children_order(root, &[b, a]).unwrap();
+        assert_eq!(t.fmt_tree(|s| s.to_string()), "root\n├── b\n│   └── b1\n└── a\n    ├── a1\n    └── a2\n");
+    }
+
+    #[test]
+    fn set_children_order_rejects_non_permutations() {
+        let (mut t, [root, a, a1, _, b, _]) = sample();
+        for bad in [&[a][..], &[a, a], &[a, b, b], &[a, a1]] {
+            assert_eq!(t.set_children_order(root, bad), Err(TreeError::NotPermutation(root)));
+        }
+        assert_eq!(t.children(root).collect::<Vec<_>>(), vec![a, b]);
+    }
+
+    #[test]
+    fn to_dot_lists_nodes_then_edges() {
+        let (t, _) = sample();
+        let dot = t.to_dot(|s| (s.to_string(), if s.len() == 1 { "box" } else { "ellipse" }));
+        assert_eq!(
+            dot,
+            concat!(
+                "digraph tree {\n",
+                "    n0 [label=\"root\", shape=ellipse];\n",
+                "    n1 [label=\"a\", shape=box];\n",
+                "    n2 [label=\"a1\", shape=ellipse];\n",
+                "    n3 [label=\"a2\", shape=ellipse];\n",
+                "    n4 [label=\"b\", shape=box];\n",
+                "    n5 [label=\"b1\", shape=ellipse];\n",
+                "    n0 -> n1;\n",
+                "    n0 -> n4;\n",
+                "    n1 -> n2;\n",
+                "    n1 -> n3;\n",
+                "    n4 -> n5;\n",
+                "}\n",
+            )
+        );
+        let quoted = Tree::from_nested(NestedNode { value: r#"say "hi" \"#, children: vec![] });
+        assert!(quoted.to_dot(|s| (s.to_string(), "box")).contains(r#"[label="say \"hi\" \\", shape=box]"#));
+    }
+
+    #[test]
+    fn prune_by_path_matches_full_paths() {
+        let t = Tree::from_nested(NestedNode {
+            value: "repo",
+            children: vec![NestedNode {
+                value: "src",
+                children: vec![
+                    NestedNode { value: "generated", children: vec![NestedNode { value: "api.rs", children: vec![] }] },
+                    NestedNode { value: "main.rs", children: vec![] },
+                ],
+            }],
+        });
+        let mut t = t.map_with_id(|_, s| s.to_string());
+
+        t.prune_by_path("/", |p| p.contains("/generated/"));
+        assert_eq!(t.fmt_tree(|s| s.clone()), "repo\n└── src\n    ├── generated\n    └── main.rs\n");
+
+        t.prune_by_path("/", |p| p.ends_with("/generated"));
+        assert_eq!(t.to_paths("/"), ["repo", "repo/src", "repo/src/main.rs"]);
+    }
+
+    #[test]
+    fn adjacency_list_is_indexed_by_slot() {
+        let (mut t, [root, a, a1, a2, b, b1]) = sample();
+        assert_eq!(t.as_adjacency_list(), vec![vec![a, b], vec![a1, a2], vec![], vec![], vec![b1], vec![]]);
+
+        t.clear_children(b);
+        let adj = t.as_adjacency_list();
+        assert_eq!(adj.len(), 6);
+        assert_eq!(adj[b.0], vec![]);
+        assert_eq!(adj[root.0], vec![a, b]);
+    }
+
+    #[test]
+    fn count_leaves_of_sample() {
+        let (mut t, [_, a, ..]) = sample();
+        assert_eq!(t.count_leaves(), 3);
+        t.clear_children(a);
+        assert_eq!(t.count_leaves(), 2);
+        assert_eq!(Tree::<u8>::new().count_leaves(), 0);
+    }
+
+    #[test]
+    fn wrap_root_adds_a_level() {
+        let (mut t, [root, _, a1, ..]) = sample();
+        let before = t.depth_stats().max;
+
+        let top = t.wrap_root("top").unwrap();
+        assert_eq!(t.root(), Some(top));
+        assert_eq!(t.parent(root), Some(top));
+        assert_eq!(t.depth_stats().max, before + 1);
+        assert_eq!(t.ancestor_count(a1), 3);
+
+        assert_eq!(Tree::new().wrap_root(1), Err(TreeError::NoRoot));
+    }
+
+    #[test]
+    fn children_slice_indexes_directly() {
+        let (t, [root, a, _, _, b, b1]) = sample();
+        let children = t.children_slice(root);
+        assert_eq!(children, &[a, b]);
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[1], b);
+        assert!(t.children_slice(b1).is_empty());
+    }
+}
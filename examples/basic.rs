@@ -1,6 +1,6 @@
 use std::fs;
 use std::path::Path;
-use pathfinder::{Tree, NodeId};
+use dir::{Tree, NodeId};
 
 /// Recursively build Tree<String> from a filesystem path
 fn build_tree_from_path(tree: &mut Tree<String>, path: &Path, parent: Option<NodeId>) -> std::io::Result<NodeId> {
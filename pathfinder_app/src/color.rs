@@ -0,0 +1,20 @@
+/// ANSI foreground colors cycled through by depth: cyan, green, yellow,
+/// magenta, blue.
+const DEPTH_PALETTE: [u8; 5] = [36, 32, 33, 35, 34];
+
+/// Wrap `label` in the ANSI color for a node at `depth`.
+pub fn paint_depth(label: &str, depth: usize) -> String {
+    format!("\x1b[{}m{label}\x1b[0m", DEPTH_PALETTE[depth % DEPTH_PALETTE.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_cycles_by_depth() {
+        assert_eq!(paint_depth("a", 0), "\x1b[36ma\x1b[0m");
+        assert_eq!(paint_depth("a", 1), "\x1b[32ma\x1b[0m");
+        assert_eq!(paint_depth("a", DEPTH_PALETTE.len()), paint_depth("a", 0));
+    }
+}
@@ -0,0 +1,36 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A filesystem error annotated with the path that caused it.
+#[derive(Debug)]
+pub struct ScanError {
+    path: PathBuf,
+    source: io::Error,
+    /// Whether the error came from writing to `path` rather than reading it.
+    writing: bool,
+}
+
+impl ScanError {
+    pub fn new(path: &Path, source: io::Error) -> Self {
+        Self { path: path.to_path_buf(), source, writing: false }
+    }
+
+    /// An error writing to `path`, such as the output stream.
+    pub fn write(path: &Path, source: io::Error) -> Self {
+        Self { writing: true, ..Self::new(path, source) }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.writing { "write" } else { "read" };
+        write!(f, "cannot {verb} '{}': {}", self.path.display(), self.source)
+    }
+}
+
+impl std::error::Error for ScanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
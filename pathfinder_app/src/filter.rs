@@ -0,0 +1,83 @@
+use std::time::{Duration, SystemTime};
+use dir::Tree;
+use dir::glob::glob_match;
+use crate::scan::Entry;
+
+/// Parse a duration such as `30s`, `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num.parse().map_err(|_| format!("invalid duration '{s}'"))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid duration unit in '{s}', expected one of s, m, h, d, w")),
+    };
+    let total = n.checked_mul(secs).ok_or_else(|| format!("duration '{s}' is too large"))?;
+    Ok(Duration::from_secs(total))
+}
+
+/// Whether `entry` was modified at or after `cutoff`.
+pub fn modified_since(entry: &Entry, cutoff: SystemTime) -> bool {
+    entry.modified.is_some_and(|m| m >= cutoff)
+}
+
+/// Drop files rejected by `keep`. Directories are left alone, even if this
+/// empties them; see [`prune_empty_dirs`].
+pub fn retain_files<F>(tree: &mut Tree<Entry>, keep: F)
+where
+    F: Fn(&Entry) -> bool,
+{
+    tree.keep_branches_matching(|e| e.is_dir || keep(e));
+}
+
+/// Remove directories that have no files anywhere beneath them. The root is
+/// always kept.
+pub fn prune_empty_dirs(tree: &mut Tree<Entry>) {
+    tree.keep_branches_matching(|e| !e.is_dir);
+}
+
+/// Whether `entry` is a file with one of the given extensions.
+pub fn has_extension(entry: &Entry, exts: &[String]) -> bool {
+    !entry.is_dir
+        && entry
+            .name
+            .rsplit_once('.')
+            .is_some_and(|(stem, ext)| !stem.is_empty() && exts.iter().any(|e| e.trim_start_matches('.') == ext))
+}
+
+/// Whether `entry` is a file whose name matches `pattern`.
+pub fn file_matches(entry: &Entry, pattern: &str) -> bool {
+    !entry.is_dir && glob_match(pattern, &entry.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_duration_units() {
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert_eq!(parse_duration("99999999999999999d"), Err("duration '99999999999999999d' is too large".to_string()));
+    }
+
+    #[test]
+    fn matches_extensions() {
+        let file = |name: &str| Entry { name: name.to_string(), ..Entry::default() };
+        let exts = vec!["rs".to_string(), ".md".to_string()];
+        assert!(has_extension(&file("main.rs"), &exts));
+        assert!(has_extension(&file("README.md"), &exts));
+        assert!(!has_extension(&file("rs"), &exts));
+        assert!(!has_extension(&file(".rs"), &exts));
+        assert!(!has_extension(&file("main.rs.bak"), &exts));
+        assert!(!has_extension(&Entry { is_dir: true, ..file("src.rs") }, &exts));
+    }
+}
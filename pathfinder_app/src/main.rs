@@ -1,50 +1,355 @@
-use std::fs;
+mod color;
+mod error;
+mod filter;
+mod quote;
+mod scan;
+mod stats;
+#[cfg(test)]
+mod testutil;
+
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use dir::{Tree, NodeId};
-use clap::Parser;
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
+use clap::{Parser, ValueEnum};
+use dir::{Connectors, Tree};
+use error::ScanError;
+use scan::{Entry, ScanOptions};
+
+/// Glyph set used to draw tree branches.
+#[derive(Clone, Copy, ValueEnum)]
+enum Charset {
+    Unicode,
+    Ascii,
+}
+
+/// Format of the main tree output.
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    /// Indented tree drawing
+    Tree,
+    /// Graphviz DOT, with directories drawn as folders
+    Dot,
+}
+
+/// When to use colors in the output.
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    /// Only when stdout is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+/// Which size `--size` shows for a directory.
+#[derive(Clone, Copy, ValueEnum)]
+enum DirSize {
+    /// The directory entry's own size
+    Own,
+    /// The sum of the sizes of everything beneath it
+    Total,
+}
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to scan
-    path: PathBuf,
+    #[arg(required_unless_present = "from_stdin")]
+    path: Option<PathBuf>,
+
+    /// Read slash-separated paths from stdin instead of scanning
+    #[arg(long, conflicts_with = "path")]
+    from_stdin: bool,
+
+    /// Label each node with its full path from the scan root
+    #[arg(long)]
+    full_path: bool,
+
+    /// Only show files modified within this long (e.g. 30m, 12h, 7d)
+    #[arg(long, value_parser = filter::parse_duration, conflicts_with = "from_stdin")]
+    newer_than: Option<Duration>,
+
+    /// Print the full path of every entry matching this glob instead of the
+    /// tree. A glob containing `/` is matched against the path below the
+    /// scan root (so `src/*.rs`, not `./src/*.rs`), any other glob against
+    /// the entry's name
+    #[arg(long, value_name = "GLOB")]
+    find: Option<String>,
+
+    /// Print tree shape metrics instead of the tree
+    #[arg(long)]
+    stats: bool,
+
+    /// Only show directories containing a file whose name matches this glob
+    #[arg(long, value_name = "GLOB", conflicts_with = "from_stdin")]
+    only_dirs_with: Option<String>,
+
+    /// Label the root (and full paths) relative to this directory
+    #[arg(long, value_name = "DIR", conflicts_with = "from_stdin")]
+    relative_to: Option<PathBuf>,
+
+    /// Only show files with this extension (repeatable)
+    #[arg(long, value_name = "EXT")]
+    ext: Vec<String>,
+
+    /// Remove directories left without files (default when a filter is active)
+    #[arg(long, overrides_with = "no_prune_empty")]
+    prune_empty: bool,
+
+    /// Keep directories even if filtering leaves them empty
+    #[arg(long, overrides_with = "prune_empty")]
+    no_prune_empty: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Output::Tree)]
+    output: Output,
+
+    /// Print one path per line instead of the tree
+    #[arg(long)]
+    flat: bool,
+
+    /// End each path with a NUL byte instead of a newline in `--flat` and
+    /// `--find` output (implies `--flat` otherwise)
+    #[arg(long)]
+    print0: bool,
+
+    /// Show names in double quotes, escaping special characters
+    #[arg(long)]
+    quote_names: bool,
+
+    /// When to color the output
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Color each name by its depth in the tree (needs colors to be enabled)
+    #[arg(long)]
+    depth_colors: bool,
+
+    /// Show each entry's size in bytes
+    #[arg(long)]
+    size: bool,
+
+    /// Size shown for directories with `--size`
+    #[arg(long, value_enum, default_value_t = DirSize::Total, requires = "size")]
+    dir_size: DirSize,
+
+    /// Characters used to draw the tree
+    #[arg(long, value_enum, default_value_t = Charset::Unicode)]
+    charset: Charset,
+
+    /// List directories before files at every level
+    #[arg(long)]
+    dirs_first: bool,
+
+    /// Report how long the scan took on stderr
+    #[arg(long)]
+    time: bool,
+
+    /// Stop descending below this many directory levels while scanning
+    #[arg(long, default_value_t = ScanOptions::default().max_scan_depth)]
+    max_scan_depth: usize,
+
+    /// Skip directories whose name matches this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude_dir: Vec<String>,
+
+    /// Skip entries whose name matches this glob; a trailing `/` matches
+    /// directories only (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Read `--exclude` globs from a file, one per line
+    #[arg(long, value_name = "FILE")]
+    exclude_from: Option<PathBuf>,
+
+    /// Descend into symlinked directories (directory loops are still skipped)
+    #[arg(long, conflicts_with = "from_stdin")]
+    follow_symlinks: bool,
+
+    /// Don't descend into directories on other filesystems (unix only)
+    #[arg(long, conflicts_with = "from_stdin")]
+    one_file_system: bool,
+
+    /// Stop scanning after this many entries (scans sequentially)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_files: Option<u64>,
+
+    /// Count entries first to allocate the tree once (scans on one thread)
+    #[arg(long, conflicts_with_all = ["threads", "max_files"])]
+    presize: bool,
+
+    /// Number of threads to scan with (defaults to the number of cores)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
 }
 
-fn main() -> std::io::Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
 
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("pathfinder: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), ScanError> {
+    let started = Instant::now();
+    let mut truncated = false;
+    let mut tree = match &args.path {
+        Some(path) => {
+            let mut opts = ScanOptions {
+                max_scan_depth: args.max_scan_depth,
+                exclude_dirs: args.exclude_dir.clone(),
+                excludes: args.exclude.clone(),
+                presize: args.presize,
+                follow_symlinks: args.follow_symlinks,
+                device: if args.one_file_system { scan::device_of(path) } else { None },
+                ..ScanOptions::default()
+            };
+            if let Some(file) = &args.exclude_from {
+                opts.excludes.extend(scan::read_patterns(file)?);
+            }
+            if let Some(n) = args.threads {
+                opts.threads = usize::from(n);
+            }
+            let mut tree = match args.max_files {
+                Some(max) => {
+                    let (tree, cut) = scan::scan_capped(path, &opts, usize::try_from(max).unwrap_or(usize::MAX))?;
+                    truncated = cut;
+                    tree
+                }
+                None => scan::scan(path, &opts)?,
+            };
+            if let (Some(base), Some(root)) = (&args.relative_to, tree.root()) {
+                tree.get_mut(root).name = scan::relative_label(path, base)?;
+            }
+            tree
+        }
+        None => {
+            let lines = io::stdin()
+                .lock()
+                .lines()
+                .collect::<io::Result<Vec<_>>>()
+                .map_err(|e| ScanError::new(Path::new("<stdin>"), e))?;
+            scan::build_tree_from_lines(&lines)
+        }
+    };
 
-    let mut tree = Tree::new();
-    build_tree_from_path(&mut tree, &args.path, None)?;
+    let elapsed = started.elapsed();
+    let scanned = tree.node_ids().len();
 
-    println!("\nTree structure:");
-    println!("{}", tree.fmt_tree(|s| s.clone()));
+    let filtered = args.newer_than.is_some() || args.only_dirs_with.is_some() || !args.ext.is_empty();
+    if let Some(age) = args.newer_than {
+        // A cutoff before the earliest representable time lets everything through
+        if let Some(cutoff) = SystemTime::now().checked_sub(age) {
+            filter::retain_files(&mut tree, |e| filter::modified_since(e, cutoff));
+        }
+    }
+    if let Some(pattern) = &args.only_dirs_with {
+        filter::retain_files(&mut tree, |e| filter::file_matches(e, pattern));
+    }
+    if !args.ext.is_empty() {
+        filter::retain_files(&mut tree, |e| filter::has_extension(e, &args.ext));
+    }
+    // --only-dirs-with is only meaningful with pruning, so it always prunes
+    let prune = if args.no_prune_empty { args.only_dirs_with.is_some() } else { args.prune_empty || filtered };
+    if prune {
+        filter::prune_empty_dirs(&mut tree);
+    }
+
+    if args.dirs_first {
+        scan::dirs_first(&mut tree);
+    }
+
+    let mut out = io::stdout().lock();
+    let mut res = print_output(args, &tree, &mut out);
+    if truncated {
+        // Keep the marker out of output meant for other programs
+        if draws_tree(args) {
+            res = res.and_then(|()| writeln!(out, "(truncated)"));
+        } else {
+            eprintln!("pathfinder: warning: output truncated by --max-files");
+        }
+    }
+    match res.and_then(|()| out.flush()) {
+        // The reader went away early, as with `| head`; nothing is lost
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        res => res.map_err(|e| ScanError::write(Path::new("<stdout>"), e))?,
+    }
 
+    if args.time {
+        let secs = elapsed.as_secs_f64();
+        eprintln!("pathfinder: scanned {scanned} entries in {secs:.3}s ({:.0} entries/s)", scanned as f64 / secs.max(1e-9));
+    }
     Ok(())
 }
 
-/// Recursively build Tree<String> from a filesystem path
-fn build_tree_from_path(tree: &mut Tree<String>, path: &Path, parent: Option<NodeId>) -> std::io::Result<NodeId> {
-    let name = path
-        .file_name()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| path.display().to_string());
+/// Whether the flags leave the default tree drawing as the output.
+fn draws_tree(args: &Args) -> bool {
+    args.find.is_none() && !args.stats && !args.flat && !args.print0 && matches!(args.output, Output::Tree)
+}
 
-    // Create node
-    let node_id = match parent {
-        Some(p) => tree.add_child(p, name),
-        None => tree.set_root(name),
-    };
+/// Write the tree, or whatever report the flags ask for instead, to `out`.
+fn print_output(args: &Args, tree: &Tree<Entry>, out: &mut impl Write) -> io::Result<()> {
+    let end = if args.print0 { '\0' } else { '\n' };
+    if let Some(pattern) = &args.find {
+        for id in tree.find_by_glob(pattern, |id| scan::relative_path_of(tree, id)) {
+            write!(out, "{}{end}", scan::path_of(tree, id))?;
+        }
+        return Ok(());
+    }
 
-    if path.is_dir() {
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let child_path = entry.path();
-            // Recursively add children
-            build_tree_from_path(tree, &child_path, Some(node_id))?;
+    if args.stats {
+        return write!(out, "{}", stats::render_stats(tree));
+    }
+
+    if args.flat || args.print0 {
+        for id in tree.dfs() {
+            write!(out, "{}{end}", scan::path_of(tree, id))?;
         }
+        return Ok(());
     }
 
-    Ok(node_id)
-}
+    if let Output::Dot = args.output {
+        return write!(out, "{}", tree.to_dot(|e| (e.name.clone(), if e.is_dir { "folder" } else { "note" })));
+    }
 
+    let mut labels = if args.full_path {
+        tree.map_with_id(|id, _| scan::path_of(tree, id))
+    } else {
+        tree.map_with_id(|_, e| e.name.clone())
+    };
+    if args.quote_names {
+        labels = labels.map_with_id(|_, label| quote::quote_name(label));
+    }
+    labels = labels.map_with_id(|id, label| match &tree.get(id).symlink_target {
+        Some(target) => format!("{label} -> {}", target.display()),
+        None => label.clone(),
+    });
+    if args.size {
+        let sizes = match args.dir_size {
+            DirSize::Own => tree.map_with_id(|_, e| e.size),
+            DirSize::Total => stats::directory_sizes(tree),
+        };
+        labels = labels.map_with_id(|id, label| format!("{label} ({} bytes)", sizes.get(id)));
+    }
+
+    let colored = match args.color {
+        ColorChoice::Auto => io::stdout().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    if args.depth_colors && colored {
+        labels = labels.map_with_id(|id, label| color::paint_depth(label, tree.ancestor_count(id)));
+    }
+
+    writeln!(out, "\nTree structure:")?;
+    let connectors = match args.charset {
+        Charset::Unicode => Connectors::unicode(),
+        Charset::Ascii => Connectors::ascii(),
+    };
+    writeln!(out, "{}", labels.fmt_tree_connectors(&connectors, |s| s.clone()))
+}
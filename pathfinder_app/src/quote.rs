@@ -0,0 +1,34 @@
+/// Wrap `name` in double quotes, backslash-escaping quotes, backslashes and
+/// control characters, like `ls -Q`.
+pub fn quote_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 2);
+    out.push('"');
+    for c in name.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.extend(c.escape_default()),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(quote_name("plain"), r#""plain""#);
+        assert_eq!(quote_name("two words"), r#""two words""#);
+        assert_eq!(quote_name(r#"say "hi"\now"#), r#""say \"hi\"\\now""#);
+        assert_eq!(quote_name("line\nbreak\x07"), r#""line\nbreak\u{7}""#);
+    }
+}
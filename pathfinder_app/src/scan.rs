@@ -0,0 +1,588 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::SystemTime;
+use dir::{Tree, NodeId};
+use dir::glob::glob_match;
+use crate::error::ScanError;
+
+/// What the scanner records about each filesystem entry.
+#[derive(Debug, Clone, Default)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub modified: Option<SystemTime>,
+    /// Length in bytes, as reported by the filesystem.
+    pub size: u64,
+    /// `(device, inode)` on unix, used to recognise hardlinks.
+    pub file_id: Option<(u64, u64)>,
+    /// Where the entry points, if it is a symlink.
+    pub symlink_target: Option<PathBuf>,
+}
+
+/// Knobs controlling how the filesystem is walked.
+pub struct ScanOptions {
+    /// Hard limit on recursion depth, guarding against pathological nesting.
+    pub max_scan_depth: usize,
+    /// Number of worker threads scanning the root's entries concurrently.
+    pub threads: usize,
+    /// Globs matched against directory names; matching directories are skipped.
+    pub exclude_dirs: Vec<String>,
+    /// Globs matched against every entry name; matching entries are skipped.
+    /// A trailing `/` restricts a pattern to directories.
+    pub excludes: Vec<String>,
+    /// Count entries up front so the arena is allocated once. Presized
+    /// scans are always sequential, whatever `threads` says.
+    pub presize: bool,
+    /// Device of the scan root when it should not be left; directories on
+    /// other devices are listed but not descended into (unix only).
+    pub device: Option<u64>,
+    /// Descend into symlinked directories. The scan root is always followed.
+    pub follow_symlinks: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self { max_scan_depth: 1000, threads, exclude_dirs: Vec::new(), excludes: Vec::new(), presize: false, device: None, follow_symlinks: false }
+    }
+}
+
+/// Scan `path` into a fresh Tree<Entry>.
+///
+/// With more than one thread, each entry directly under `path` is scanned
+/// by a worker into its own tree and grafted back in directory order, so
+/// the result is the same as a sequential scan.
+pub fn scan(path: &Path, opts: &ScanOptions) -> Result<Tree<Entry>, ScanError> {
+    // Fail early with the offending path instead of printing a lone root
+    fs::metadata(path).map_err(|e| ScanError::new(path, e))?;
+
+    if opts.presize {
+        return build_tree_presized(path, opts);
+    }
+    if opts.threads <= 1 {
+        let mut tree = Tree::new();
+        build_tree_from_path(&mut tree, path, None, 0, opts, &mut Budget::unlimited(), &mut Ancestors::new())?;
+        return Ok(tree);
+    }
+
+    let mut tree = Tree::new();
+    let entry = read_entry(path, true);
+    let is_dir = entry.is_dir;
+    let ancestors: Ancestors = entry.file_id.into_iter().collect();
+    let root = tree.set_root(entry);
+    if is_dir {
+        let children = scan_parallel(&list_dir(path, 0, opts)?, 1, opts, &ancestors)?;
+        for sub in children {
+            let end = tree.children(root).count();
+            tree.graft_at_index(sub, root, end);
+        }
+    }
+    Ok(tree)
+}
+
+/// Sequential scan of `path` that stops once `max_entries` nodes (at least
+/// the root) have been added. Also returns whether entries were left out.
+pub fn scan_capped(path: &Path, opts: &ScanOptions, max_entries: usize) -> Result<(Tree<Entry>, bool), ScanError> {
+    fs::metadata(path).map_err(|e| ScanError::new(path, e))?;
+    let mut budget = Budget { remaining: max_entries.saturating_sub(1), exhausted: false };
+    let mut tree = Tree::new();
+    build_tree_from_path(&mut tree, path, None, 0, opts, &mut budget, &mut Ancestors::new())?;
+    Ok((tree, budget.exhausted))
+}
+
+/// How many more entries a scan may add beyond the ones already in the
+/// tree, and whether it has had to turn any away.
+struct Budget {
+    remaining: usize,
+    exhausted: bool,
+}
+
+impl Budget {
+    fn unlimited() -> Self {
+        Self { remaining: usize::MAX, exhausted: false }
+    }
+
+    /// Claim room for one more entry, if there is any left.
+    fn take(&mut self) -> bool {
+        if self.remaining == 0 {
+            self.exhausted = true;
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+}
+
+/// Two-phase sequential scan: count the entries under `path`, then build
+/// the tree into an arena allocated for exactly that many nodes.
+pub fn build_tree_presized(path: &Path, opts: &ScanOptions) -> Result<Tree<Entry>, ScanError> {
+    let mut tree = Tree::with_capacity(count_entries(path, 0, opts, &mut Ancestors::new()));
+    build_tree_from_path(&mut tree, path, None, 0, opts, &mut Budget::unlimited(), &mut Ancestors::new())?;
+    Ok(tree)
+}
+
+/// Number of nodes a scan of `path` would produce. Unreadable directories
+/// count as leaves here; the real scan reports the error.
+fn count_entries(path: &Path, depth: usize, opts: &ScanOptions, ancestors: &mut Ancestors) -> usize {
+    if depth >= opts.max_scan_depth {
+        return 1;
+    }
+    let entry = read_entry(path, depth == 0 || opts.follow_symlinks);
+    if !entry.is_dir || on_other_device(&entry, opts) {
+        return 1;
+    }
+    // The real scan warns about loops; counting just stops at them
+    if entry.file_id.is_some_and(|fid| !ancestors.insert(fid)) {
+        return 1;
+    }
+    let Ok(entries) = fs::read_dir(path) else { return 1 };
+    let count = 1 + entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| !is_excluded(p, opts))
+        .map(|p| count_entries(&p, depth + 1, opts, ancestors))
+        .sum::<usize>();
+    if let Some(fid) = entry.file_id {
+        ancestors.remove(&fid);
+    }
+    count
+}
+
+/// `(device, inode)` of the directories from the scan root down to the one
+/// being scanned, used to recognise directory loops.
+type Ancestors = HashSet<(u64, u64)>;
+
+/// Whether the directory `entry` at `path` is one of its own ancestors, so
+/// that descending into it would loop. Warns when it is.
+fn is_loop(entry: &Entry, path: &Path, ancestors: &Ancestors) -> bool {
+    let looped = entry.file_id.is_some_and(|fid| ancestors.contains(&fid));
+    if looped {
+        eprintln!("pathfinder: warning: not descending into '{}': directory loop detected", path.display());
+    }
+    looped
+}
+
+/// Recursively build Tree<Entry> from a filesystem path
+fn build_tree_from_path(
+    tree: &mut Tree<Entry>,
+    path: &Path,
+    parent: Option<NodeId>,
+    depth: usize,
+    opts: &ScanOptions,
+    budget: &mut Budget,
+    ancestors: &mut Ancestors,
+) -> Result<NodeId, ScanError> {
+    let entry = read_entry(path, depth == 0 || opts.follow_symlinks);
+    let descend = entry.is_dir && !on_other_device(&entry, opts) && !is_loop(&entry, path, ancestors);
+    let file_id = entry.file_id;
+
+    // Create node
+    let node_id = match parent {
+        Some(p) => tree.add_child(p, entry),
+        None => tree.set_root(entry),
+    };
+
+    if descend {
+        ancestors.extend(file_id);
+        for child_path in list_dir(path, depth, opts)? {
+            if !budget.take() {
+                break;
+            }
+            // Recursively add children
+            build_tree_from_path(tree, &child_path, Some(node_id), depth + 1, opts, budget, ancestors)?;
+        }
+        if let Some(fid) = file_id {
+            ancestors.remove(&fid);
+        }
+    }
+
+    Ok(node_id)
+}
+
+/// Scan each path into its own tree on a pool of `opts.threads` workers,
+/// returning the trees in the same order as `paths`, which all sit under
+/// `ancestors`.
+fn scan_parallel(paths: &[PathBuf], depth: usize, opts: &ScanOptions, ancestors: &Ancestors) -> Result<Vec<Tree<Entry>>, ScanError> {
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<_>>> = paths.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|s| {
+        for _ in 0..opts.threads.min(paths.len()) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else { break };
+                let mut sub = Tree::new();
+                let res = build_tree_from_path(&mut sub, path, None, depth, opts, &mut Budget::unlimited(), &mut ancestors.clone())
+                    .map(|_| sub);
+                *results[i].lock().unwrap() = Some(res);
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|slot| slot.into_inner().unwrap().expect("every path is scanned"))
+        .collect()
+}
+
+/// Read what we record about a single path, without descending into it.
+/// A symlink describes its target when `follow` is set, otherwise itself,
+/// so a link to a directory is not a directory.
+fn read_entry(path: &Path, follow: bool) -> Entry {
+    let name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+
+    let link_meta = fs::symlink_metadata(path);
+    let is_link = link_meta.as_ref().is_ok_and(|m| m.file_type().is_symlink());
+    let symlink_target = if is_link { fs::read_link(path).ok() } else { None };
+
+    // Broken symlinks have no target metadata; describe the link instead
+    let meta = if is_link && follow { fs::metadata(path).or(link_meta) } else { link_meta };
+    match meta {
+        Ok(meta) => Entry {
+            name,
+            is_dir: meta.is_dir(),
+            modified: meta.modified().ok(),
+            size: meta.len(),
+            file_id: file_id(&meta),
+            symlink_target,
+        },
+        Err(_) => Entry { name, symlink_target, ..Entry::default() },
+    }
+}
+
+#[cfg(unix)]
+fn file_id(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Device id of `path`, for [`ScanOptions::device`].
+pub fn device_of(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().and_then(|meta| file_id(&meta)).map(|(dev, _)| dev)
+}
+
+/// Whether `entry` lives on a different device than the one the scan is
+/// confined to. Always false when the scan may cross devices.
+fn on_other_device(entry: &Entry, opts: &ScanOptions) -> bool {
+    match (opts.device, entry.file_id) {
+        (Some(root), Some((dev, _))) => dev != root,
+        _ => false,
+    }
+}
+
+/// List the entries of the directory at `path`, which sits at `depth`.
+/// Returns nothing once the scan depth limit has been reached.
+fn list_dir(path: &Path, depth: usize, opts: &ScanOptions) -> Result<Vec<PathBuf>, ScanError> {
+    if depth >= opts.max_scan_depth {
+        eprintln!(
+            "pathfinder: warning: not descending into '{}': scan depth limit of {} reached",
+            path.display(),
+            opts.max_scan_depth
+        );
+        return Ok(Vec::new());
+    }
+
+    let mut children = Vec::new();
+    for entry in fs::read_dir(path).map_err(|e| ScanError::new(path, e))? {
+        let child = entry.map_err(|e| ScanError::new(path, e))?.path();
+        if !is_excluded(&child, opts) {
+            children.push(child);
+        }
+    }
+    Ok(children)
+}
+
+/// Whether `path` matches one of the `--exclude` globs, or is a directory
+/// matching one of the `--exclude-dir` globs.
+fn is_excluded(path: &Path, opts: &ScanOptions) -> bool {
+    let Some(name) = path.file_name() else { return false };
+    let name = name.to_string_lossy();
+    if opts.excludes.iter().any(|g| !g.ends_with('/') && glob_match(g, &name)) {
+        return true;
+    }
+    let mut dir_globs = opts
+        .exclude_dirs
+        .iter()
+        .map(String::as_str)
+        .chain(opts.excludes.iter().filter_map(|g| g.strip_suffix('/')));
+    // Match the name first so the extra stat only happens for candidates
+    dir_globs.any(|g| glob_match(g, &name)) && {
+        let meta = if opts.follow_symlinks { fs::metadata(path) } else { fs::symlink_metadata(path) };
+        meta.is_ok_and(|m| m.is_dir())
+    }
+}
+
+/// Read exclude globs from a file, one per line. Blank lines and lines
+/// starting with `#` are ignored.
+pub fn read_patterns(path: &Path) -> Result<Vec<String>, ScanError> {
+    let text = fs::read_to_string(path).map_err(|e| ScanError::new(path, e))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Label for a scan root at `path` as seen from `base`: the relative path
+/// if `path` is inside `base` (`.` if they are the same), otherwise the
+/// absolute path.
+pub fn relative_label(path: &Path, base: &Path) -> Result<String, ScanError> {
+    let path = fs::canonicalize(path).map_err(|e| ScanError::new(path, e))?;
+    let base = fs::canonicalize(base).map_err(|e| ScanError::new(base, e))?;
+    let label = match path.strip_prefix(&base) {
+        Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+        Ok(rel) => rel.to_string_lossy().to_string(),
+        Err(_) => path.to_string_lossy().to_string(),
+    };
+    Ok(label)
+}
+
+/// `/`-joined names from the root down to `id`. A root label that already
+/// ends in `/`, like `./` or `/`, is not given a second one.
+pub fn path_of(tree: &Tree<Entry>, id: NodeId) -> String {
+    let mut segments = vec![tree.get(id).name.as_str()];
+    let mut cur = tree.parent(id);
+    while let Some(p) = cur {
+        segments.push(&tree.get(p).name);
+        cur = tree.parent(p);
+    }
+    segments.reverse();
+    if segments.len() > 1 {
+        segments[0] = segments[0].trim_end_matches('/');
+    }
+    segments.join("/")
+}
+
+/// `/`-joined names below the root down to `id`, i.e. its path relative to
+/// the scan root. The root itself has no such path, so it is just its name.
+pub fn relative_path_of(tree: &Tree<Entry>, id: NodeId) -> String {
+    let mut segments = vec![tree.get(id).name.as_str()];
+    let mut cur = tree.parent(id);
+    while let Some(p) = cur.filter(|&p| tree.parent(p).is_some()) {
+        segments.push(&tree.get(p).name);
+        cur = tree.parent(p);
+    }
+    segments.reverse();
+    segments.join("/")
+}
+
+/// Reorder every node's children so directories come before files. The
+/// existing order is otherwise kept.
+pub fn dirs_first(tree: &mut Tree<Entry>) {
+    for id in tree.dfs() {
+        tree.sort_children_by(id, |a, b| b.is_dir.cmp(&a.is_dir));
+    }
+}
+
+/// Build Tree<Entry> from slash-separated paths, sharing common prefixes.
+///
+/// If every path starts with the same segment it becomes the root,
+/// otherwise the paths are gathered under a synthetic `.` root. Absolute
+/// paths start with a `/` segment, so they stay absolute. Any segment with
+/// something beneath it is recorded as a directory.
+pub fn build_tree_from_lines<S: AsRef<str>>(lines: &[S]) -> Tree<Entry> {
+    let paths: Vec<Vec<&str>> = lines
+        .iter()
+        .map(|l| {
+            let l = l.as_ref();
+            let root = l.starts_with('/').then_some("/");
+            root.into_iter().chain(l.split('/').filter(|s| !s.is_empty())).collect::<Vec<_>>()
+        })
+        .filter(|segs| !segs.is_empty())
+        .collect();
+
+    let mut tree = Tree::new();
+    let Some(first) = paths.first() else { return tree };
+
+    let shared_root = paths.iter().all(|segs| segs[0] == first[0]);
+    let root_name = if shared_root { first[0] } else { "." };
+    let root = tree.set_root(Entry { name: root_name.to_string(), ..Entry::default() });
+    let skip = usize::from(shared_root);
+
+    for segs in &paths {
+        let mut node = root;
+        for &seg in &segs[skip..] {
+            tree.get_mut(node).is_dir = true;
+            let existing = tree.child_matching(node, |e| e.name == seg);
+            node = match existing {
+                Some(c) => c,
+                None => tree.add_child(node, Entry { name: seg.to_string(), ..Entry::default() }),
+            };
+        }
+    }
+
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::fixture;
+
+    fn names(tree: &Tree<Entry>) -> Vec<&str> {
+        tree.dfs().into_iter().map(|id| tree.get(id).name.as_str()).collect()
+    }
+
+    #[test]
+    fn lines_share_common_prefixes() {
+        let tree = build_tree_from_lines(&["a/b/c", "a/b/d", "a/e"]);
+        assert_eq!(names(&tree), vec!["a", "b", "c", "d", "e"]);
+        let dirs: Vec<_> = tree.dfs().into_iter().filter(|&id| tree.get(id).is_dir).collect();
+        assert_eq!(dirs.iter().map(|&id| tree.get(id).name.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn lines_without_common_root_get_dot_root() {
+        let tree = build_tree_from_lines(&["x/1", "y/2"]);
+        assert_eq!(names(&tree), vec![".", "x", "1", "y", "2"]);
+    }
+
+    #[test]
+    fn scan_depth_guard_stops_descending() {
+        let root = fixture("deep");
+        let mut deep = root.clone();
+        for i in 0..10 {
+            deep.push(format!("d{i}"));
+        }
+        fs::create_dir_all(&deep).unwrap();
+
+        let tree = scan(&root, &ScanOptions { max_scan_depth: 3, threads: 1, ..ScanOptions::default() }).unwrap();
+
+        // root plus three levels of directories, the last left unexpanded
+        assert_eq!(names(&tree)[1..], ["d0", "d1", "d2"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn device_boundary_predicate() {
+        let dir = |dev| Entry { is_dir: true, file_id: Some((dev, 7)), ..Entry::default() };
+        let confined = ScanOptions { device: Some(1), ..ScanOptions::default() };
+
+        assert!(!on_other_device(&dir(1), &confined));
+        assert!(on_other_device(&dir(2), &confined));
+        assert!(!on_other_device(&Entry { file_id: None, ..dir(2) }, &confined));
+        assert!(!on_other_device(&dir(2), &ScanOptions::default()));
+    }
+
+    #[test]
+    fn dirs_first_at_every_level() {
+        let root = fixture("dirs-first");
+        for d in ["one", "one/two"] {
+            fs::create_dir_all(root.join(d)).unwrap();
+            for f in ["a.txt", "z.txt"] {
+                fs::write(root.join(d).join(f), "").unwrap();
+            }
+        }
+        fs::create_dir(root.join("three")).unwrap();
+        fs::write(root.join("b.txt"), "").unwrap();
+
+        let mut tree = scan(&root, &ScanOptions { threads: 1, ..ScanOptions::default() }).unwrap();
+        dirs_first(&mut tree);
+
+        for id in tree.dfs() {
+            let kinds: Vec<bool> = tree.children_data(id).map(|e| e.is_dir).collect();
+            assert!(kinds.windows(2).all(|w| w[0] >= w[1]), "{}: {kinds:?}", tree.get(id).name);
+        }
+        assert_eq!(tree.children(tree.root().unwrap()).count(), 3);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn capped_scan_stops_at_limit() {
+        let root = fixture("capped");
+        for d in 0..5 {
+            let dir = root.join(format!("d{d}"));
+            fs::create_dir(&dir).unwrap();
+            for f in 0..20 {
+                fs::write(dir.join(format!("f{f}")), "").unwrap();
+            }
+        }
+        let opts = ScanOptions::default();
+
+        let (tree, truncated) = scan_capped(&root, &opts, 30).unwrap();
+        assert_eq!(tree.dfs().len(), 30);
+        assert!(truncated);
+
+        let (tree, truncated) = scan_capped(&root, &opts, 106).unwrap();
+        assert_eq!(tree.dfs().len(), 106);
+        assert!(!truncated);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn directory_loops_are_not_followed() {
+        use std::os::unix::fs::symlink;
+        let root = fixture("loops");
+        fs::create_dir(root.join("real")).unwrap();
+        fs::write(root.join("real").join("f"), "").unwrap();
+        symlink(".", root.join("real").join("self")).unwrap();
+        symlink(".", root.join("l1")).unwrap();
+        symlink(".", root.join("l2")).unwrap();
+
+        for threads in [1, 4] {
+            let opts = ScanOptions { threads, follow_symlinks: true, ..ScanOptions::default() };
+            let tree = scan(&root, &opts).unwrap();
+            let mut seen = names(&tree)[1..].to_vec();
+            seen.sort();
+            assert_eq!(seen, ["f", "l1", "l2", "real", "self"], "threads: {threads}");
+            assert_eq!(count_entries(&root, 0, &opts, &mut Ancestors::new()), tree.dfs().len());
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directories_are_not_descended_by_default() {
+        let root = fixture("symlinked-dir");
+        fs::create_dir(root.join("real")).unwrap();
+        fs::write(root.join("real").join("f"), "").unwrap();
+        std::os::unix::fs::symlink("real", root.join("link")).unwrap();
+
+        let tree = scan(&root, &ScanOptions::default()).unwrap();
+        let link = tree.child_matching(tree.root().unwrap(), |e| e.name == "link").unwrap();
+        assert!(!tree.get(link).is_dir);
+        assert!(tree.is_leaf(link));
+        assert_eq!(tree.get(link).symlink_target.as_deref(), Some(Path::new("real")));
+
+        let followed = scan(&root, &ScanOptions { follow_symlinks: true, ..ScanOptions::default() }).unwrap();
+        let link = followed.child_matching(followed.root().unwrap(), |e| e.name == "link").unwrap();
+        assert_eq!(followed.children_data(link).map(|e| e.name.as_str()).collect::<Vec<_>>(), ["f"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn presized_scan_matches_normal_scan() {
+        let root = fixture("presized");
+        for d in ["a", "b", "c"] {
+            fs::create_dir_all(root.join(d).join("inner")).unwrap();
+            fs::write(root.join(d).join("inner").join("f.txt"), "").unwrap();
+        }
+        let opts = ScanOptions { threads: 1, ..ScanOptions::default() };
+
+        let normal = scan(&root, &opts).unwrap();
+        let presized = build_tree_presized(&root, &opts).unwrap();
+
+        assert_eq!(names(&presized), names(&normal));
+        assert_eq!(count_entries(&root, 0, &opts, &mut Ancestors::new()), normal.dfs().len());
+
+        // Presizing wins over the default thread count
+        let many = scan(&root, &ScanOptions { presize: true, threads: 4, ..ScanOptions::default() }).unwrap();
+        assert_eq!(names(&many), names(&normal));
+        assert_eq!(many.capacity(), normal.dfs().len());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
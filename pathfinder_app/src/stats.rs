@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use dir::Tree;
+use crate::scan::{self, Entry};
+
+/// Render a short report of the tree's shape.
+pub fn render_stats(tree: &Tree<Entry>) -> String {
+    let depth = tree.depth_stats();
+    let mut dirs = 0;
+    let mut files = 0;
+    let mut largest = None;
+    let mut deepest = None;
+
+    let mut stack: Vec<_> = tree.root().map(|r| (r, 0)).into_iter().collect();
+    while let Some((id, d)) = stack.pop() {
+        if tree.get(id).is_dir {
+            dirs += 1;
+            let n = tree.children(id).count();
+            if largest.is_none_or(|(_, best)| n > best) {
+                largest = Some((id, n));
+            }
+        } else {
+            files += 1;
+        }
+        if deepest.is_none_or(|(_, best)| d > best) {
+            deepest = Some((id, d));
+        }
+        // Reverse so siblings pop in order and ties go to the first one
+        let children: Vec<_> = tree.children(id).collect();
+        stack.extend(children.into_iter().rev().map(|c| (c, d + 1)));
+    }
+
+    let mut out = String::new();
+    writeln!(out, "max depth: {}", depth.max).unwrap();
+    writeln!(out, "directories: {dirs}").unwrap();
+    writeln!(out, "files: {files}").unwrap();
+    writeln!(out, "total size: {} bytes", unique_size(tree)).unwrap();
+    if let Some((id, n)) = largest {
+        writeln!(out, "largest directory: {} ({n} entries)", tree.get(id).name).unwrap();
+    }
+    if let Some((id, _)) = deepest {
+        writeln!(out, "deepest path: {}", scan::path_of(tree, id)).unwrap();
+    }
+    out
+}
+
+/// Total size of all files, counting hardlinked copies of a file only once.
+pub fn unique_size(tree: &Tree<Entry>) -> u64 {
+    let mut seen = HashSet::new();
+    tree.dfs()
+        .into_iter()
+        .map(|id| tree.get(id))
+        .filter(|e| !e.is_dir)
+        .filter(|e| e.file_id.is_none_or(|fid| seen.insert(fid)))
+        .map(|e| e.size)
+        .sum()
+}
+
+/// Size of every node, keeping the ids of `tree`: a file's own length, or
+/// the sum of the sizes of everything beneath a directory.
+pub fn directory_sizes(tree: &Tree<Entry>) -> Tree<u64> {
+    let mut sizes = tree.map_with_id(|_, e| if e.is_dir { 0 } else { e.size });
+    sizes.reduce_in_place(|size, children| *size += children.iter().copied().sum::<u64>());
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::scan::{self, ScanOptions};
+    use crate::testutil::fixture;
+
+    #[cfg(unix)]
+    #[test]
+    fn unique_size_counts_hardlinks_once() {
+        let root = fixture("hardlinks");
+        fs::write(root.join("a.bin"), [0u8; 100]).unwrap();
+        fs::hard_link(root.join("a.bin"), root.join("b.bin")).unwrap();
+        fs::write(root.join("c.bin"), [0u8; 10]).unwrap();
+
+        let tree = scan::scan(&root, &ScanOptions::default()).unwrap();
+
+        assert_eq!(unique_size(&tree), 110);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn directory_sizes_sum_contents() {
+        let root = fixture("dir-sizes");
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join("sub").join("a"), [0u8; 30]).unwrap();
+        fs::write(root.join("b"), [0u8; 5]).unwrap();
+
+        let tree = scan::scan(&root, &ScanOptions::default()).unwrap();
+        let sizes = directory_sizes(&tree);
+
+        let sub = tree.children(tree.root().unwrap()).find(|&c| tree.get(c).name == "sub").unwrap();
+        assert_eq!(*sizes.get(sub), 30);
+        assert_eq!(*sizes.get(tree.root().unwrap()), 35);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,10 @@
+use std::fs;
+use std::path::PathBuf;
+
+/// Fresh, empty scratch directory unique to this test process.
+pub fn fixture(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pathfinder-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
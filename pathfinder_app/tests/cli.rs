@@ -0,0 +1,517 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+fn pathfinder() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_pathfinder_app"))
+}
+
+/// Fresh, empty scratch directory unique to this test process.
+fn fixture(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pathfinder-cli-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Node labels of a rendered tree, with connector glyphs stripped.
+fn labels(rendered: &str) -> Vec<&str> {
+    rendered
+        .lines()
+        .map(|l| l.trim_start_matches(|c| "│├└─ ".contains(c)))
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+#[test]
+fn nonexistent_path_reports_error() {
+    let missing = std::env::temp_dir().join("pathfinder-does-not-exist");
+    let out = pathfinder().arg(&missing).output().unwrap();
+
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains(&*missing.to_string_lossy()), "stderr: {stderr}");
+}
+
+#[test]
+fn from_stdin_builds_tree_from_paths() {
+    let mut child = pathfinder()
+        .arg("--from-stdin")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"a/b/c\na/b/d\na/e\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(stdout, "\nTree structure:\na\n├── b\n│   ├── c\n│   └── d\n└── e\n\n");
+}
+
+#[test]
+fn from_stdin_keeps_absolute_paths_absolute() {
+    let mut child = pathfinder()
+        .args(["--from-stdin", "--flat"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"/etc\n/etc/hosts\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "/\n/etc\n/etc/hosts\n");
+}
+
+#[test]
+fn full_path_labels_leaves_with_joined_path() {
+    let root = fixture("full-path");
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src").join("main.rs"), "").unwrap();
+
+    let out = pathfinder().arg(&root).arg("--full-path").output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let root_name = root.file_name().unwrap().to_string_lossy();
+    let leaf = format!("{root_name}/src/main.rs");
+    assert!(stdout.lines().any(|l| l.ends_with(&leaf)), "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn newer_than_hides_old_files_and_empty_dirs() {
+    let root = fixture("newer-than");
+    fs::create_dir(root.join("fresh")).unwrap();
+    fs::create_dir(root.join("stale")).unwrap();
+    fs::write(root.join("fresh").join("new.txt"), "").unwrap();
+    fs::write(root.join("fresh").join("old.txt"), "").unwrap();
+    fs::write(root.join("stale").join("ancient.txt"), "").unwrap();
+    let past = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+    for old in [root.join("fresh").join("old.txt"), root.join("stale").join("ancient.txt")] {
+        fs::File::options().write(true).open(old).unwrap().set_modified(past).unwrap();
+    }
+
+    let out = pathfinder().arg(&root).args(["--newer-than", "7d"]).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let shown = labels(&stdout);
+    assert!(shown.contains(&"fresh") && shown.contains(&"new.txt"), "stdout: {stdout}");
+    assert!(!shown.contains(&"old.txt"), "stdout: {stdout}");
+    assert!(!shown.contains(&"stale") && !shown.contains(&"ancient.txt"), "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn stats_reports_tree_shape() {
+    let root = fixture("stats");
+    fs::create_dir_all(root.join("src").join("util")).unwrap();
+    fs::write(root.join("a.txt"), "").unwrap();
+    fs::write(root.join("src").join("main.rs"), "").unwrap();
+    fs::write(root.join("src").join("lib.rs"), "").unwrap();
+    fs::write(root.join("src").join("util").join("x.rs"), "").unwrap();
+
+    let out = pathfinder().arg(&root).arg("--stats").output().unwrap();
+
+    assert!(out.status.success());
+    let root_name = root.file_name().unwrap().to_string_lossy();
+    let expected = format!(
+        "max depth: 3\n\
+         directories: 3\n\
+         files: 4\n\
+         total size: 0 bytes\n\
+         largest directory: src (3 entries)\n\
+         deepest path: {root_name}/src/util/x.rs\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&out.stdout), expected);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn threaded_scan_matches_single_thread() {
+    let root = fixture("threads");
+    for d in ["a", "b", "c", "d", "e"] {
+        fs::create_dir_all(root.join(d).join("inner")).unwrap();
+        fs::write(root.join(d).join("file.txt"), "").unwrap();
+        fs::write(root.join(d).join("inner").join("deep.txt"), "").unwrap();
+    }
+    fs::write(root.join("top.txt"), "").unwrap();
+
+    let single = pathfinder().arg(&root).args(["--threads", "1"]).output().unwrap();
+    let multi = pathfinder().arg(&root).args(["--threads", "4"]).output().unwrap();
+
+    assert!(single.status.success() && multi.status.success());
+    assert_eq!(single.stdout, multi.stdout);
+    assert_eq!(labels(&String::from_utf8_lossy(&single.stdout)).len(), 1 + 1 + 5 * 4 + 1);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn find_prints_matching_paths() {
+    let root = fixture("find");
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src").join("main.rs"), "").unwrap();
+    fs::write(root.join("src").join("README.md"), "").unwrap();
+    fs::write(root.join("build.rs"), "").unwrap();
+
+    let out = pathfinder().arg(&root).args(["--find", "*.rs"]).output().unwrap();
+
+    assert!(out.status.success());
+    let root_name = root.file_name().unwrap().to_string_lossy().into_owned();
+    let mut found: Vec<String> = String::from_utf8_lossy(&out.stdout).lines().map(String::from).collect();
+    found.sort();
+    assert_eq!(found, vec![format!("{root_name}/build.rs"), format!("{root_name}/src/main.rs")]);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn root_with_trailing_slash_is_not_doubled() {
+    let root = fixture("trailing-slash");
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src").join("glob.rs"), "").unwrap();
+
+    let out = pathfinder().current_dir(&root).args(["./", "--flat"]).output().unwrap();
+    let found = pathfinder().current_dir(&root).args(["./", "--find", "*.rs"]).output().unwrap();
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "./\n./src\n./src/glob.rs\n");
+    assert_eq!(String::from_utf8_lossy(&found.stdout), "./src/glob.rs\n");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn find_with_slash_matches_below_scan_root() {
+    let root = fixture("find-slash");
+    fs::create_dir_all(root.join("src").join("bin")).unwrap();
+    fs::write(root.join("src").join("main.rs"), "").unwrap();
+    fs::write(root.join("src").join("bin").join("tool.rs"), "").unwrap();
+    fs::write(root.join("build.rs"), "").unwrap();
+
+    let find = |dir: &str, pattern: &str| {
+        let out = pathfinder().current_dir(&root).arg(dir).args(["--find", pattern]).output().unwrap();
+        assert!(out.status.success());
+        String::from_utf8_lossy(&out.stdout).into_owned()
+    };
+
+    assert_eq!(find(".", "src/*.rs"), "./src/main.rs\n");
+    assert_eq!(find("src", "bin/*.rs"), "src/bin/tool.rs\n");
+    assert_eq!(find(".", "**/bin/*.rs"), "./src/bin/tool.rs\n");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn only_dirs_with_keeps_matching_branches() {
+    let root = fixture("only-dirs-with");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("src").join("lib.rs"), "").unwrap();
+    fs::write(root.join("docs").join("guide.md"), "").unwrap();
+
+    let out = pathfinder().arg(&root).args(["--only-dirs-with", "*.rs"]).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let shown = labels(&stdout);
+    assert!(shown.contains(&"src") && shown.contains(&"lib.rs"), "stdout: {stdout}");
+    assert!(!shown.contains(&"docs") && !shown.contains(&"guide.md"), "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn exclude_dir_skips_only_directories() {
+    let root = fixture("exclude-dir");
+    fs::create_dir_all(root.join("build").join("out")).unwrap();
+    fs::write(root.join("build").join("out").join("app.o"), "").unwrap();
+    fs::create_dir(root.join("scripts")).unwrap();
+    fs::write(root.join("scripts").join("build"), "").unwrap();
+
+    let out = pathfinder().arg(&root).args(["--exclude-dir", "build", "--exclude-dir", "tmp*"]).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let shown = labels(&stdout);
+    assert!(shown.contains(&"scripts") && shown.contains(&"build"), "stdout: {stdout}");
+    assert!(!shown.contains(&"out") && !shown.contains(&"app.o"), "stdout: {stdout}");
+    assert_eq!(shown.iter().filter(|l| **l == "build").count(), 1, "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn prune_empty_follows_filters_unless_disabled() {
+    let root = fixture("prune-empty");
+    fs::create_dir(root.join("src")).unwrap();
+    fs::create_dir(root.join("docs")).unwrap();
+    fs::write(root.join("src").join("main.rs"), "").unwrap();
+    fs::write(root.join("docs").join("guide.md"), "").unwrap();
+
+    let pruned = pathfinder().arg(&root).args(["--ext", "rs"]).output().unwrap();
+    let kept = pathfinder().arg(&root).args(["--ext", "rs", "--no-prune-empty"]).output().unwrap();
+
+    assert!(pruned.status.success() && kept.status.success());
+    let pruned = String::from_utf8_lossy(&pruned.stdout);
+    let kept = String::from_utf8_lossy(&kept.stdout);
+    assert!(labels(&pruned).contains(&"main.rs") && !labels(&pruned).contains(&"docs"), "stdout: {pruned}");
+    assert!(labels(&kept).contains(&"docs") && !labels(&kept).contains(&"guide.md"), "stdout: {kept}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn charset_ascii_uses_ascii_connectors() {
+    let root = fixture("charset");
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("sub").join("inner.txt"), "").unwrap();
+    fs::write(root.join("top.txt"), "").unwrap();
+
+    let out = pathfinder().arg(&root).args(["--charset", "ascii", "--dirs-first"]).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("|-- sub\n|   `-- inner.txt\n`-- top.txt\n"), "stdout: {stdout}");
+    assert!(stdout.is_ascii(), "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn relative_to_labels_root_from_base() {
+    let base = fixture("relative-to");
+    let nested = base.join("nested").join("deep");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(nested.join("leaf.txt"), "").unwrap();
+
+    let out = pathfinder().arg(&nested).arg("--relative-to").arg(&base).arg("--full-path").output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(labels(&stdout)[1..], ["nested/deep", "nested/deep/leaf.txt"], "stdout: {stdout}");
+    fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn time_reports_on_stderr() {
+    let root = fixture("time");
+    fs::write(root.join("a.txt"), "").unwrap();
+
+    let out = pathfinder().arg(&root).arg("--time").output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(labels(&stdout).contains(&"a.txt"), "stdout: {stdout}");
+    assert!(!stdout.contains("entries/s"), "stdout: {stdout}");
+    assert!(stderr.contains("scanned 2 entries in ") && stderr.contains("entries/s"), "stderr: {stderr}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn exclude_from_reads_patterns_file() {
+    let root = fixture("exclude-from");
+    fs::create_dir(root.join("tmp")).unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("tmp").join("scratch.txt"), "").unwrap();
+    fs::write(root.join("src").join("debug.log"), "").unwrap();
+    fs::write(root.join("src").join("main.rs"), "").unwrap();
+    fs::write(root.join("notes"), "").unwrap();
+    let patterns = root.with_extension("patterns");
+    fs::write(&patterns, "# scratch output\n*.log\n\ntmp/\n").unwrap();
+
+    let out = pathfinder().arg(&root).arg("--exclude-from").arg(&patterns).args(["--exclude", "notes"]).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let shown = labels(&stdout);
+    assert!(shown.contains(&"main.rs"), "stdout: {stdout}");
+    for gone in ["tmp", "scratch.txt", "debug.log", "notes"] {
+        assert!(!shown.contains(&gone), "{gone} in stdout: {stdout}");
+    }
+    fs::remove_file(&patterns).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn max_files_caps_and_marks_output() {
+    let root = fixture("max-files");
+    for i in 0..50 {
+        fs::write(root.join(format!("f{i:02}")), "").unwrap();
+    }
+
+    let out = pathfinder().arg(&root).args(["--max-files", "10"]).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let shown = labels(&stdout);
+    // heading, root, nine files and the marker
+    assert_eq!(shown.len(), 12, "stdout: {stdout}");
+    assert_eq!(shown.last(), Some(&"(truncated)"));
+
+    for mode in [&["--output", "dot"][..], &["--print0"], &["--stats"], &["--find", "*"]] {
+        let out = pathfinder().arg(&root).args(["--max-files", "2"]).args(mode).output().unwrap();
+        assert!(out.status.success());
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(!stdout.contains("truncated"), "{mode:?} stdout: {stdout}");
+        assert!(stderr.contains("truncated"), "{mode:?} stderr: {stderr}");
+    }
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn print0_separates_paths_with_nul() {
+    let root = fixture("print0");
+    fs::write(root.join("two words.txt"), "").unwrap();
+    fs::write(root.join("plain.txt"), "").unwrap();
+
+    let out = pathfinder().arg(&root).arg("--print0").output().unwrap();
+
+    assert!(out.status.success());
+    assert!(!out.stdout.contains(&b'\n'));
+    let root_name = root.file_name().unwrap().to_string_lossy();
+    let mut paths: Vec<_> = out.stdout.split(|&b| b == 0).map(String::from_utf8_lossy).collect();
+    assert_eq!(paths.pop().as_deref(), Some(""), "output ends with a NUL");
+    paths.sort();
+    assert_eq!(paths, [root_name.to_string(), format!("{root_name}/plain.txt"), format!("{root_name}/two words.txt")]);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn closed_stdout_exits_cleanly() {
+    let mut child = pathfinder()
+        .args(["--from-stdin", "--flat"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Close the read end before any output, like `| head` exiting early
+    drop(child.stdout.take());
+    let lines: String = (0..20_000).map(|i| format!("root/dir-{}/file-{i}.txt\n", i % 100)).collect();
+    child.stdin.take().unwrap().write_all(lines.as_bytes()).unwrap();
+    let out = child.wait_with_output().unwrap();
+
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    assert!(out.stderr.is_empty(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+}
+
+#[test]
+fn dir_size_own_or_total() {
+    let root = fixture("dir-size");
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("sub").join("a.bin"), [0u8; 30]).unwrap();
+    fs::write(root.join("sub").join("b.bin"), [0u8; 12]).unwrap();
+    let own = fs::metadata(root.join("sub")).unwrap().len();
+
+    let run = |mode: &str| {
+        let out = pathfinder().arg(&root).args(["--size", "--dir-size", mode]).output().unwrap();
+        assert!(out.status.success());
+        String::from_utf8_lossy(&out.stdout).into_owned()
+    };
+    let total = run("total");
+    let own_out = run("own");
+    let without_size = pathfinder().arg(&root).args(["--dir-size", "own"]).output().unwrap();
+
+    assert!(labels(&total).contains(&"sub (42 bytes)"), "stdout: {total}");
+    assert!(labels(&own_out).contains(&format!("sub ({own} bytes)").as_str()), "stdout: {own_out}");
+    assert!(labels(&own_out).contains(&"a.bin (30 bytes)"), "stdout: {own_out}");
+    assert!(!without_size.status.success());
+    assert!(String::from_utf8_lossy(&without_size.stderr).contains("--size"));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn quote_names_wraps_labels() {
+    let root = fixture("quote-names");
+    fs::write(root.join("two words.txt"), "").unwrap();
+
+    let out = pathfinder().arg(&root).arg("--quote-names").output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(labels(&stdout).contains(&"\"two words.txt\""), "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn depth_colors_tint_each_level() {
+    let root = fixture("depth-colors");
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("sub").join("leaf.txt"), "").unwrap();
+
+    let forced = pathfinder().arg(&root).args(["--depth-colors", "--color", "always"]).output().unwrap();
+    let piped = pathfinder().arg(&root).arg("--depth-colors").output().unwrap();
+
+    assert!(forced.status.success() && piped.status.success());
+    let forced = String::from_utf8_lossy(&forced.stdout);
+    assert!(forced.contains("\x1b[32msub\x1b[0m"), "stdout: {forced:?}");
+    assert!(forced.contains("\x1b[33mleaf.txt\x1b[0m"), "stdout: {forced:?}");
+    assert!(!String::from_utf8_lossy(&piped.stdout).contains('\x1b'));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn output_dot_emits_graphviz() {
+    let root = fixture("output-dot");
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src").join("main.rs"), "").unwrap();
+
+    let out = pathfinder().arg(&root).args(["--output", "dot"]).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.first(), Some(&"digraph tree {"), "stdout: {stdout}");
+    assert_eq!(lines.last(), Some(&"}"), "stdout: {stdout}");
+    assert!(lines.contains(&r#"    n1 [label="src", shape=folder];"#), "stdout: {stdout}");
+    assert!(lines.contains(&r#"    n2 [label="main.rs", shape=note];"#), "stdout: {stdout}");
+    assert!(lines.contains(&"    n0 -> n1;") && lines.contains(&"    n1 -> n2;"), "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_show_their_target() {
+    let root = fixture("symlinks");
+    fs::write(root.join("real.txt"), "").unwrap();
+    std::os::unix::fs::symlink("real.txt", root.join("link")).unwrap();
+    std::os::unix::fs::symlink("missing", root.join("dangling")).unwrap();
+    fs::create_dir(root.join("dir")).unwrap();
+    fs::write(root.join("dir").join("inside.txt"), "").unwrap();
+    std::os::unix::fs::symlink("dir", root.join("dirlink")).unwrap();
+
+    let out = pathfinder().arg(&root).output().unwrap();
+
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let shown = labels(&stdout);
+    assert!(shown.contains(&"link -> real.txt"), "stdout: {stdout}");
+    assert!(shown.contains(&"dangling -> missing"), "stdout: {stdout}");
+    assert!(shown.contains(&"real.txt"), "stdout: {stdout}");
+    assert!(shown.contains(&"dirlink -> dir"), "stdout: {stdout}");
+    // Only the real directory's copy of its contents is listed
+    assert_eq!(shown.iter().filter(|&&l| l == "inside.txt").count(), 1, "stdout: {stdout}");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn presize_scans_sequentially_without_threads() {
+    let root = fixture("presize");
+    for d in ["a", "b", "c"] {
+        fs::create_dir(root.join(d)).unwrap();
+        fs::write(root.join(d).join("f.txt"), "").unwrap();
+    }
+
+    let presized = pathfinder().arg(&root).arg("--presize").output().unwrap();
+    let sequential = pathfinder().arg(&root).args(["--threads", "1"]).output().unwrap();
+    let both = pathfinder().arg(&root).args(["--presize", "--threads", "4"]).output().unwrap();
+    let capped = pathfinder().arg(&root).args(["--presize", "--max-files", "2"]).output().unwrap();
+
+    assert!(presized.status.success(), "stderr: {}", String::from_utf8_lossy(&presized.stderr));
+    assert_eq!(presized.stdout, sequential.stdout);
+    assert!(!both.status.success());
+    assert!(String::from_utf8_lossy(&both.stderr).contains("--threads"));
+    assert!(!capped.status.success());
+    assert!(String::from_utf8_lossy(&capped.stderr).contains("--max-files"));
+    fs::remove_dir_all(&root).unwrap();
+}